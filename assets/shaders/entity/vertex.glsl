@@ -0,0 +1,32 @@
+#version 450
+
+// model vertex, see BlockVertex
+layout(location = 0) in vec3 position; // relative to the entity's origin, before its transform
+layout(location = 1) in vec2 tex_coords;
+layout(location = 2) in vec4 atlas_rect;
+
+// per instance, see EntityInstance
+layout(location = 3) in mat4 model; // takes up locations 3 to 6
+layout(location = 7) in vec2 light; // sky and block light, 0 to 1
+
+layout(location = 0) out vec2 frag_tex_coords;
+layout(location = 1) flat out vec4 frag_atlas_rect;
+layout(location = 2) out vec2 frag_light;
+layout(location = 3) out float frag_ao;
+
+// MVP uniform, its model matrix is replaced by the instance's
+layout(set = 0, binding = 0) uniform MVP {
+    mat4 model;
+    mat4 view;
+    mat4 proj;
+} mvp;
+
+void main() {
+    mat4 upside_down = mat4(1, 0, 0, 0, 0, -1, 0, 0, 0, 0, -1, 0, 0, 0, 0, 1);
+
+    frag_tex_coords = tex_coords;
+    frag_atlas_rect = atlas_rect;
+    frag_light = light;
+    frag_ao = 1.0;
+    gl_Position = mvp.proj * upside_down * mvp.view * model * vec4(position, 1.0);
+}
@@ -2,13 +2,30 @@
 
 // layout(location = 0) in vec3 frag_color;
 layout(location = 0) in vec2 frag_tex_coords;
+layout(location = 1) flat in vec4 frag_atlas_rect;
+layout(location = 2) in vec2 frag_light; // sky and block light, 0 to 1
+layout(location = 3) in float frag_ao; // ambient occlusion, 1 is unoccluded
 
 layout(location = 0) out vec4 out_color;
 
 layout(set = 0, binding = 1) uniform sampler2D tex_sampler;
 
 void main() {
-    vec4 tex = texture(tex_sampler, frag_tex_coords);
-    out_color = tex;
+    // Greedy meshed quads span several blocks, so wrap the tile coords back into the atlas entry
+    vec2 atlas_uv = frag_atlas_rect.xy + fract(frag_tex_coords) * frag_atlas_rect.zw;
+    // fract() jumps at every block edge, so the mip level is picked from the unwrapped coords
+    vec2 uv_dx = dFdx(frag_tex_coords) * frag_atlas_rect.zw;
+    vec2 uv_dy = dFdy(frag_tex_coords) * frag_atlas_rect.zw;
+    vec4 tex = textureGrad(tex_sampler, atlas_uv, uv_dx, uv_dy);
+#ifdef ALPHA_TEST
+    // cutout blocks (leaves, plants) are either fully there or not at all
+    if (tex.a < 0.5) {
+        discard;
+    }
+#endif
+    // every light level below full is 20% darker than the one above it
+    float light_level = max(frag_light.x, frag_light.y) * 15.0;
+    float brightness = pow(0.8, 15.0 - light_level);
+    out_color = vec4(tex.rgb * brightness * frag_ao, tex.a);
     //out_color = tex_color * vec4(frag_color, 1.0); // This would tint the texture with vertex color, not needed.
 }
@@ -1,9 +1,15 @@
 #version 450
 
-layout(location = 0) in vec3 position;
-layout(location = 1) in vec2 tex_coords;
+// packed chunk vertex, see ChunkVertex
+layout(location = 0) in uvec4 position; // chunk local, 1/256 block steps
+layout(location = 1) in ivec2 tex_coords; // 1/256 tile steps
+layout(location = 2) in vec4 atlas_rect;
+layout(location = 3) in vec4 shade; // sky light, block light, ambient occlusion
 
 layout(location = 0) out vec2 frag_tex_coords;
+layout(location = 1) flat out vec4 frag_atlas_rect;
+layout(location = 2) out vec2 frag_light;
+layout(location = 3) out float frag_ao;
 
 // MVP uniform
 layout(set = 0, binding = 0) uniform MVP {
@@ -12,9 +18,19 @@ layout(set = 0, binding = 0) uniform MVP {
     mat4 proj;
 } mvp;
 
+// world position of each drawn chunk's (0, 0, 0) corner, indexed by the draw's first instance
+layout(set = 1, binding = 0) readonly buffer ChunkOrigins {
+    vec4 chunk_origins[];
+};
+
 void main() {
     mat4 upside_down = mat4(1, 0, 0, 0, 0, -1, 0, 0, 0, 0, -1, 0, 0, 0, 0, 1);
 
-    frag_tex_coords = tex_coords;
-    gl_Position = mvp.proj * upside_down * mvp.view * mvp.model * vec4(position, 1.0);
+    vec3 world_position = chunk_origins[gl_InstanceIndex].xyz + vec3(position.xyz) / 256.0;
+
+    frag_tex_coords = vec2(tex_coords) / 256.0;
+    frag_atlas_rect = atlas_rect;
+    frag_light = shade.xy;
+    frag_ao = shade.z;
+    gl_Position = mvp.proj * upside_down * mvp.view * mvp.model * vec4(world_position, 1.0);
 }
@@ -0,0 +1,17 @@
+#version 450
+
+layout(location = 0) in vec3 position; // around the unit box of a block at the origin
+layout(location = 1) in vec3 offset; // per instance, see OutlineInstance
+
+// MVP uniform
+layout(set = 0, binding = 0) uniform MVP {
+    mat4 model;
+    mat4 view;
+    mat4 proj;
+} mvp;
+
+void main() {
+    mat4 upside_down = mat4(1, 0, 0, 0, 0, -1, 0, 0, 0, 0, -1, 0, 0, 0, 0, 1);
+
+    gl_Position = mvp.proj * upside_down * mvp.view * mvp.model * vec4(position + offset, 1.0);
+}
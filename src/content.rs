@@ -1,2 +1,3 @@
 pub(crate) mod block;
+pub(crate) mod entity;
 pub(crate) mod world;
@@ -1,2 +1,3 @@
 pub(crate) mod block;
-pub(crate) mod block_registry;
\ No newline at end of file
+pub(crate) mod block_registry;
+pub(crate) mod block_state;
\ No newline at end of file
@@ -1,5 +1,6 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use serde::Deserialize;
+use crate::content::block::block_state::{self, StateProperties};
 use crate::engine::rendering::texture_atlas::AtlasUV;
 use crate::engine::resources::load_json5_file;
 
@@ -7,20 +8,123 @@ use crate::engine::resources::load_json5_file;
 pub struct Block {
     pub id: String,
 
+    #[serde(default)]
+    pub properties: BlockProperties,
+
+    /// Property name -> allowed values. The first value is the property's default.
+    #[serde(default)]
+    pub state_properties: BTreeMap<String, Vec<String>>,
+
     #[serde(default)]
     pub block_states: BlockStates,
 }
 
+/// How a block behaves, the same for all of its states
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct BlockProperties {
+    /// Entities collide with the block
+    pub solid: bool,
+    /// Nothing behind the block's full faces can be seen, so touching faces of neighbors get
+    /// culled, and light doesn't pass through it
+    pub opaque: bool,
+    pub render_layer: RenderLayer,
+    /// Light level the block gives off, 0 to 15
+    pub light_emission: u8,
+    /// How hard the block is to break. Negative means it can't be broken.
+    pub hardness: f32,
+    /// How much the block slows down things sliding on it, 0 is frictionless
+    pub friction: f32,
+    /// Placing a block on this one replaces it instead of going next to it (like tall grass)
+    pub replaceable: bool,
+}
+
+impl Default for BlockProperties {
+    fn default() -> Self {
+        Self {
+            solid: true,
+            opaque: true,
+            render_layer: RenderLayer::Opaque,
+            light_emission: 0,
+            hardness: 1.0,
+            friction: 0.6,
+            replaceable: false,
+        }
+    }
+}
+
+impl BlockProperties {
+    pub const MAX_LIGHT_LEVEL: u8 = 15;
+
+    /// Checks for values that are out of range or contradict each other
+    pub fn validate(&self) -> Result<(), String> {
+        if self.light_emission > Self::MAX_LIGHT_LEVEL {
+            return Err(format!(
+                "light_emission is {}, but can be at most {}",
+                self.light_emission,
+                Self::MAX_LIGHT_LEVEL
+            ));
+        }
+        if self.hardness.is_nan() {
+            return Err("hardness is not a number".to_string());
+        }
+        if !self.friction.is_finite() || self.friction < 0.0 {
+            return Err(format!("friction is {}, but has to be 0 or more", self.friction));
+        }
+        if self.opaque && self.render_layer != RenderLayer::Opaque {
+            return Err(format!(
+                "opaque blocks have to use the opaque render layer, not {:?}",
+                self.render_layer
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn is_unbreakable(&self) -> bool {
+        self.hardness < 0.0
+    }
+}
+
+/// How a block's faces are drawn
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum RenderLayer {
+    /// Fully opaque textures
+    #[default]
+    Opaque,
+    /// Pixels are either fully opaque or fully transparent (leaves, plants)
+    Cutout,
+    /// Alpha blended (glass, water, ice)
+    Translucent,
+}
+
+impl RenderLayer {
+    /// In drawing order: translucent faces have to go last so they blend over everything else
+    pub const ALL: [RenderLayer; 3] = [RenderLayer::Opaque, RenderLayer::Cutout, RenderLayer::Translucent];
+    pub const COUNT: usize = Self::ALL.len();
+}
+
 #[derive(Deserialize, Debug, Clone, Default)]
 pub struct BlockStates {
     #[serde(default)]
     pub default: Option<BlockState>,
+    /// Models of specific property combinations, keyed like `axis=x` or `facing=north,half=top`.
+    /// States without a matching variant use `default`.
+    #[serde(default)]
+    pub variants: HashMap<String, BlockState>,
 }
 
 #[derive(Deserialize, Debug, Clone, Default)]
 pub struct BlockState {
     #[serde(default)]
     pub model_name: String, // path to model JSON
+    /// Model rotation in degrees around each axis, in steps of 90. Applied x first, then y, then z.
+    #[serde(default)]
+    pub x: i32,
+    #[serde(default)]
+    pub y: i32,
+    #[serde(default)]
+    pub z: i32,
     #[serde(skip)]
     pub model: Option<BlockModel>, // loaded at runtime
 }
@@ -29,6 +133,8 @@ impl Default for Block {
     fn default() -> Self {
         Self {
             id: String::new(),
+            properties: BlockProperties::default(),
+            state_properties: BTreeMap::new(),
             block_states: BlockStates::default(),
         }
     }
@@ -36,7 +142,13 @@ impl Default for Block {
 
 impl Block {
     pub fn load_model(&mut self) {
-        if let Some(state) = &mut self.block_states.default {
+        let states = self
+            .block_states
+            .default
+            .iter_mut()
+            .chain(self.block_states.variants.values_mut());
+
+        for state in states {
             if !state.model_name.is_empty() {
                 // path relative to assets/models/blocks/
                 let path = format!("assets/models/blocks/{}", state.model_name);
@@ -46,13 +158,240 @@ impl Block {
             }
         }
     }
+
+    /// Properties of the block's default state, the first value of every property
+    pub fn default_properties(&self) -> StateProperties {
+        self.state_properties
+            .iter()
+            .filter_map(|(name, values)| Some((name.clone(), values.first()?.clone())))
+            .collect()
+    }
+
+    /// Palette key of the default state
+    pub fn default_state_key(&self) -> String {
+        block_state::state_key(&self.id, &self.default_properties())
+    }
+
+    /// Fills in missing properties with their defaults and drops ones the block doesn't have
+    /// or values it doesn't allow, so any key for this block turns into a valid state.
+    pub fn normalize_properties(&self, properties: &StateProperties) -> StateProperties {
+        self.state_properties
+            .iter()
+            .filter_map(|(name, values)| {
+                let value = properties
+                    .get(name)
+                    .filter(|value| values.contains(value))
+                    .or(values.first())?;
+                Some((name.clone(), value.clone()))
+            })
+            .collect()
+    }
+
+    /// Every combination of property values the block can have
+    pub fn all_properties(&self) -> Vec<StateProperties> {
+        let mut states = vec![StateProperties::new()];
+        for (name, values) in &self.state_properties {
+            states = states
+                .iter()
+                .flat_map(|state| {
+                    values.iter().map(move |value| {
+                        let mut state = state.clone();
+                        state.insert(name.clone(), value.clone());
+                        state
+                    })
+                })
+                .collect();
+        }
+        states
+    }
+
+    /// The blockstate entry (model and rotation) used for the given properties
+    pub fn state(&self, properties: &StateProperties) -> Option<&BlockState> {
+        self.block_states
+            .variants
+            .get(&block_state::properties_key(properties))
+            .or(self.block_states.default.as_ref())
+    }
+}
+
+impl BlockState {
+    /// Rotates a direction from model space into block space
+    pub fn rotate(&self, direction: [i32; 3]) -> [i32; 3] {
+        let quarter_turns = |degrees: i32| (degrees / 90).rem_euclid(4);
+
+        let mut v = direction;
+        for _ in 0..quarter_turns(self.x) {
+            v = [v[0], -v[2], v[1]];
+        }
+        for _ in 0..quarter_turns(self.y) {
+            v = [v[2], v[1], -v[0]];
+        }
+        for _ in 0..quarter_turns(self.z) {
+            v = [-v[1], v[0], v[2]];
+        }
+        v
+    }
+}
+
+/// The six faces of a block. North is -z, south +z, west -x and east +x.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum BlockFace {
+    Top,
+    Bottom,
+    North,
+    South,
+    West,
+    East,
+}
+
+impl BlockFace {
+    /// Direction the face points in
+    pub fn normal(self) -> [i32; 3] {
+        match self {
+            BlockFace::Top => [0, 1, 0],
+            BlockFace::Bottom => [0, -1, 0],
+            BlockFace::North => [0, 0, -1],
+            BlockFace::South => [0, 0, 1],
+            BlockFace::West => [-1, 0, 0],
+            BlockFace::East => [1, 0, 0],
+        }
+    }
+
+    pub fn opposite(self) -> BlockFace {
+        match self {
+            BlockFace::Top => BlockFace::Bottom,
+            BlockFace::Bottom => BlockFace::Top,
+            BlockFace::North => BlockFace::South,
+            BlockFace::South => BlockFace::North,
+            BlockFace::West => BlockFace::East,
+            BlockFace::East => BlockFace::West,
+        }
+    }
+
+    /// The face pointing in the given direction, if it is one of the six axis directions
+    pub fn from_normal(normal: [i32; 3]) -> Option<BlockFace> {
+        BlockFace::ALL.into_iter().find(|face| face.normal() == normal)
+    }
+
+    pub const ALL: [BlockFace; 6] = [
+        BlockFace::Top,
+        BlockFace::Bottom,
+        BlockFace::North,
+        BlockFace::South,
+        BlockFace::West,
+        BlockFace::East,
+    ];
+
+    /// Model texture keys that can texture this face, most specific first
+    pub fn texture_keys(self) -> &'static [&'static str] {
+        match self {
+            BlockFace::Top => &["top", "all"],
+            BlockFace::Bottom => &["bottom", "all"],
+            BlockFace::North => &["north", "side", "all"],
+            BlockFace::South => &["south", "side", "all"],
+            BlockFace::West => &["west", "side", "all"],
+            BlockFace::East => &["east", "side", "all"],
+        }
+    }
 }
 
 /// Block model definition
 #[derive(Deserialize, Debug, Clone)]
 pub struct BlockModel {
+    /// Texture paths keyed by face (`top`, `bottom`, `side`, `north`, ...) or `all`.
+    /// Elements can also refer to any other name defined here.
     #[serde(default)]
     pub textures: HashMap<String, String>,
+
+    /// Shape used when the model has no `elements`
+    #[serde(default)]
+    pub shape: ModelShape,
+
+    /// Boxes making up the model. Replaces `shape` when not empty.
+    #[serde(default)]
+    pub elements: Vec<ModelElement>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelShape {
+    /// A full block, textured per face
+    #[default]
+    Cube,
+    /// Two crossed diagonal quads, visible from both sides, for plants.
+    /// Textured with `cross`, or any texture if the model has none.
+    Cross,
+}
+
+/// Axis-aligned box of a model, in pixels: a block goes from 0 to 16 on every axis
+#[derive(Deserialize, Debug, Clone)]
+pub struct ModelElement {
+    pub from: [f32; 3],
+    pub to: [f32; 3],
+    /// Faces of the box that get drawn. Missing faces are left out.
+    #[serde(default)]
+    pub faces: HashMap<BlockFace, ElementFace>,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct ElementFace {
+    /// Name in the model's `textures`, a leading `#` is allowed.
+    /// Uses the texture of the block face it points at when left empty.
+    #[serde(default)]
+    pub texture: String,
+    /// Part of the texture to show as `[u1, v1, u2, v2]` in pixels (0..16).
+    /// By default the face shows the part of the texture matching where the element sits in
+    /// the block, so a slab's side shows the lower half of a full block's side.
+    #[serde(default)]
+    pub uv: Option<[f32; 4]>,
+    /// Skips the face when the neighbor on that side of the block covers it completely
+    #[serde(default)]
+    pub cullface: Option<BlockFace>,
+}
+
+impl BlockModel {
+    /// Moves every element's `from` and `to` inside the block (0 to 16), since chunk meshes can't
+    /// hold geometry that reaches past the chunk. Returns whether any element stuck out.
+    pub fn clamp_elements_to_block(&mut self) -> bool {
+        let mut clamped = false;
+        for element in &mut self.elements {
+            for c in element.from.iter_mut().chain(element.to.iter_mut()) {
+                let inside = if c.is_nan() { 0.0 } else { c.clamp(0.0, 16.0) };
+                clamped |= inside != *c;
+                *c = inside;
+            }
+        }
+        clamped
+    }
+
+    /// Texture path defined under `name`, which may start with `#`
+    pub fn texture(&self, name: &str) -> Option<&String> {
+        self.textures.get(name.trim_start_matches('#'))
+    }
+
+    /// Texture path of the cross shape
+    pub fn cross_texture(&self) -> Option<&String> {
+        self.texture("cross").or_else(|| self.textures.values().next())
+    }
+
+    /// Texture path of an element face
+    pub fn element_face_texture(&self, face: BlockFace, element_face: &ElementFace) -> Option<&String> {
+        if element_face.texture.is_empty() {
+            self.face_texture(face)
+        } else {
+            self.texture(&element_face.texture)
+        }
+    }
+
+    /// Texture path of a face, trying the keys from `BlockFace::texture_keys` in order.
+    /// Models that have none of them use any texture they define.
+    pub fn face_texture(&self, face: BlockFace) -> Option<&String> {
+        face.texture_keys()
+            .iter()
+            .find_map(|key| self.textures.get(*key))
+            .or_else(|| self.textures.values().next())
+    }
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -1,4 +1,5 @@
 use crate::content::block::block::{Block, BlockModel};
+use crate::content::block::block_state::{parse_properties_key, properties_key};
 use crate::utils::registry::Registry;
 use crate::engine::resources::{load_json5_dir, load_json5_file};
 use std::collections::HashMap;
@@ -9,10 +10,35 @@ pub fn load_blocks() -> (Registry<Block>, Registry<BlockModel>) {
 
     // load all block definitions
     for mut block in load_json5_dir::<Block>("assets/data/blocks") {
-        if let Some(state) = &mut block.block_states.default {
+        if let Err(e) = block.properties.validate() {
+            eprintln!("Invalid properties of block {}: {e}", block.id);
+            continue;
+        }
+
+        // variant keys can list their properties in any order, sort them like state keys
+        block.block_states.variants = block
+            .block_states
+            .variants
+            .into_iter()
+            .map(|(key, state)| (properties_key(&parse_properties_key(&key)), state))
+            .collect();
+
+        let states = block
+            .block_states
+            .default
+            .iter_mut()
+            .chain(block.block_states.variants.values_mut());
+
+        for state in states {
             if !state.model_name.is_empty() {
                 let model_path = format!("assets/models/blocks/{}", state.model_name);
-                let model: BlockModel = load_json5_file(&model_path);
+                let mut model: BlockModel = load_json5_file(&model_path);
+                if model.clamp_elements_to_block() {
+                    eprintln!(
+                        "Elements of model {} stick out of the block, clamped them to 0..16",
+                        state.model_name
+                    );
+                }
 
                 // register model in models registry
                 block_models.register(&state.model_name, model.clone());
@@ -0,0 +1,43 @@
+use std::collections::BTreeMap;
+
+/// Block state properties, sorted by name so the same state always gives the same key
+pub type StateProperties = BTreeMap<String, String>;
+
+/// Builds the key a block state is stored under in chunk palettes: the block ID followed by its
+/// properties, e.g. `template:log[axis=x]`. Blocks without properties are just their ID.
+pub fn state_key(block_id: &str, properties: &StateProperties) -> String {
+    if properties.is_empty() {
+        return block_id.to_string();
+    }
+    format!("{block_id}[{}]", properties_key(properties))
+}
+
+/// Splits a state key back into the block ID and its properties
+pub fn parse_state_key(key: &str) -> (&str, StateProperties) {
+    let Some((block_id, rest)) = key.split_once('[') else {
+        return (key, StateProperties::new());
+    };
+    (block_id, parse_properties_key(rest.trim_end_matches(']')))
+}
+
+/// The block ID part of a state key
+pub fn block_id(key: &str) -> &str {
+    key.split_once('[').map_or(key, |(block_id, _)| block_id)
+}
+
+/// Formats properties as `name=value,name=value`, the format of blockstate variant keys
+pub fn properties_key(properties: &StateProperties) -> String {
+    properties
+        .iter()
+        .map(|(name, value)| format!("{name}={value}"))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Parses `name=value,name=value`. Entries without a `=` are skipped.
+pub fn parse_properties_key(key: &str) -> StateProperties {
+    key.split(',')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .collect()
+}
@@ -0,0 +1,2 @@
+pub mod dropped_item;
+pub mod player;
@@ -0,0 +1,73 @@
+use crate::engine::ecs::components::{Collider, Renderable, Transform, Velocity};
+use crate::engine::ecs::ecs_world::{EcsWorld, Entity};
+use crate::engine::ecs::systems::SystemContext;
+use crate::utils::math::Aabb;
+use glam::{Quat, Vec3};
+
+/// Width, height and depth of a dropped item, which is drawn as its block shrunk to this size
+pub const DROPPED_ITEM_SIZE: f32 = 0.25;
+/// Upwards speed a dropped item pops out of a broken block with
+const POP_SPEED: f32 = 4.0;
+/// How fast dropped items turn around, in radians per second
+const SPIN_SPEED: f32 = 1.5;
+/// How close the player's box has to come to a dropped item to pick it up
+const PICKUP_RANGE: f32 = 1.0;
+
+/// Marks an entity as a block lying in the world, waiting to be picked up
+pub struct DroppedItem;
+
+/// Drops `state` as an item from the block space at `block_pos`
+pub fn spawn_dropped_item(ecs: &mut EcsWorld, state: &str, block_pos: [i32; 3]) -> Entity {
+    let [x, y, z] = block_pos;
+    let position = Vec3::new(x as f32 + 0.5, y as f32 + 0.5, z as f32 + 0.5);
+
+    let entity = ecs.spawn();
+    ecs.insert(
+        entity,
+        Transform {
+            scale: Vec3::splat(DROPPED_ITEM_SIZE),
+            ..Transform::from_position(position)
+        },
+    );
+    ecs.insert(entity, Velocity(Vec3::Y * POP_SPEED));
+    ecs.insert(entity, Collider::new(Vec3::splat(DROPPED_ITEM_SIZE)));
+    ecs.insert(entity, Renderable { model: state.to_string() });
+    ecs.insert(entity, DroppedItem);
+    entity
+}
+
+/// Update system turning dropped items slowly around so they stand out
+pub fn spin_dropped_items(ecs: &mut EcsWorld, context: &SystemContext) {
+    let mut transforms = ecs.storage_mut::<Transform>();
+    let items = ecs.storage::<DroppedItem>();
+    let spin = Quat::from_rotation_y(SPIN_SPEED * context.delta_time);
+    for (_, transform, _) in transforms.join_mut(&items) {
+        transform.rotation = spin * transform.rotation;
+    }
+}
+
+/// Removes the dropped items within reach of `player_box`. There's no inventory to put them in
+/// yet, so they're just gone.
+pub fn pick_up_dropped_items(ecs: &mut EcsWorld, player_box: &Aabb) {
+    let reach = Aabb::new(
+        player_box.min - Vec3::splat(PICKUP_RANGE),
+        player_box.max + Vec3::splat(PICKUP_RANGE),
+    );
+    let picked_up: Vec<Entity> = {
+        let colliders = ecs.storage::<Collider>();
+        let transforms = ecs.storage::<Transform>();
+        let items = ecs.storage::<DroppedItem>();
+        transforms
+            .join(&items)
+            .filter(|(entity, transform, _)| {
+                colliders
+                    .get(*entity)
+                    .is_some_and(|collider| collider.aabb(transform.position).intersects(&reach))
+            })
+            .map(|(entity, _, _)| entity)
+            .collect()
+    };
+    for entity in picked_up {
+        ecs.despawn(entity);
+    }
+}
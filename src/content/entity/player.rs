@@ -0,0 +1,198 @@
+use crate::content::block::block::BlockProperties;
+use crate::content::block::block_state;
+use crate::content::world::collision::{GRAVITY, TERMINAL_VELOCITY};
+use crate::content::world::world::World;
+use crate::engine::core::content_loader::GameContent;
+use crate::engine::core::input::{InputState, KeyBind};
+use crate::utils::math::Aabb;
+use glam::{Quat, Vec3};
+
+/// Width and depth of the player's collision box
+pub const PLAYER_WIDTH: f32 = 0.6;
+pub const PLAYER_HEIGHT: f32 = 1.8;
+/// Height of the camera above the player's feet
+pub const EYE_HEIGHT: f32 = 1.62;
+
+const WALK_SPEED: f32 = 4.3;
+const SPRINT_SPEED: f32 = 5.6;
+/// Upwards speed of a jump, enough to get on top of one block
+const JUMP_SPEED: f32 = 8.0;
+/// How fast walking speeds up and slows down on ground with a friction of 1, in blocks per second
+/// squared. Slippery ground scales it down.
+const GROUND_ACCELERATION: f32 = 80.0;
+/// How fast walking speeds up and slows down in the air, no matter what's below
+const AIR_ACCELERATION: f32 = 10.0;
+
+/// The player's body in the world. Walks on solid blocks, falls and jumps, and stays out of
+/// solid blocks by sweeping its collision box against them. In noclip mode it doesn't move
+/// itself and the camera flies freely instead.
+#[derive(Clone, Copy, Debug)]
+pub struct Player {
+    /// Centre of the bottom of the collision box
+    pub position: Vec3,
+    /// `position` before the last fixed update, for drawing between updates
+    pub previous_position: Vec3,
+    pub velocity: Vec3,
+    pub on_ground: bool,
+    pub noclip: bool,
+}
+
+impl Player {
+    pub fn from_eye_position(eye_position: Vec3) -> Self {
+        let position = eye_position - Vec3::Y * EYE_HEIGHT;
+        Self {
+            position,
+            previous_position: position,
+            velocity: Vec3::ZERO,
+            on_ground: false,
+            noclip: false,
+        }
+    }
+
+    /// Eye position `alpha` of the way from the previous fixed update to the last one
+    pub fn interpolated_eye_position(&self, alpha: f32) -> Vec3 {
+        self.previous_position.lerp(self.position, alpha) + Vec3::Y * EYE_HEIGHT
+    }
+
+    pub fn aabb(&self) -> Aabb {
+        let half_width = PLAYER_WIDTH / 2.0;
+        Aabb::new(
+            self.position - Vec3::new(half_width, 0.0, half_width),
+            self.position + Vec3::new(half_width, PLAYER_HEIGHT, half_width),
+        )
+    }
+
+    /// Switches between walking and noclip. Leaving noclip puts the player's eyes where the
+    /// camera flew to, at rest.
+    pub fn set_noclip(&mut self, noclip: bool, eye_position: Vec3) {
+        if self.noclip && !noclip {
+            *self = Self::from_eye_position(eye_position);
+        }
+        self.noclip = noclip;
+    }
+
+    /// Steps the movement by `delta_time`. Walks relative to the yaw of `view_rotation`.
+    pub fn fixed_update(
+        &mut self,
+        delta_time: f32,
+        input: &InputState,
+        view_rotation: Quat,
+        world: &World,
+        content: &GameContent,
+    ) {
+        self.previous_position = self.position;
+        if self.noclip {
+            return;
+        }
+        let pressed = |keybind: &str| input.get_keybind_is_pressed(KeyBind::new(keybind));
+
+        // walking direction on the ground plane
+        let forward = (view_rotation * Vec3::Z).with_y(0.0).normalize_or_zero();
+        let right = (view_rotation * Vec3::X).with_y(0.0).normalize_or_zero();
+        let mut wish = Vec3::ZERO;
+        if pressed("MoveForward") {
+            wish += forward;
+        }
+        if pressed("MoveBackward") {
+            wish -= forward;
+        }
+        if pressed("MoveRight") {
+            wish += right;
+        }
+        if pressed("MoveLeft") {
+            wish -= right;
+        }
+        let speed = if pressed("Sprint") { SPRINT_SPEED } else { WALK_SPEED };
+        let target = wish.normalize_or_zero() * speed;
+
+        // speed up or slow down towards the walking velocity, as quickly as the ground allows
+        let acceleration = if self.on_ground {
+            GROUND_ACCELERATION * self.ground_friction(world, content)
+        } else {
+            AIR_ACCELERATION
+        };
+        let horizontal = self.velocity.with_y(0.0);
+        let change = target - horizontal;
+        let max_change = acceleration * delta_time;
+        let horizontal = if change.length() <= max_change {
+            target
+        } else {
+            horizontal + change.normalize() * max_change
+        };
+        self.velocity = horizontal.with_y(self.velocity.y);
+
+        if self.on_ground && pressed("MoveUp") {
+            self.velocity.y = JUMP_SPEED;
+        }
+        self.velocity.y = (self.velocity.y - GRAVITY * delta_time).max(-TERMINAL_VELOCITY);
+
+        let motion = self.velocity * delta_time;
+        let (aabb, blocked) = world.sweep_aabb(self.aabb(), motion, content);
+        let centre = (aabb.min + aabb.max) / 2.0;
+        self.position = centre.with_y(aabb.min.y);
+        for (axis, blocked) in blocked.into_iter().enumerate() {
+            if blocked {
+                self.velocity[axis] = 0.0;
+            }
+        }
+        self.on_ground = blocked[1] && motion.y < 0.0;
+    }
+
+    /// Friction of the block under the middle of the player. Standing on an edge with nothing
+    /// under the middle uses the default friction.
+    fn ground_friction(&self, world: &World, content: &GameContent) -> f32 {
+        let below = (self.position - Vec3::Y * 0.5).floor().as_ivec3();
+        world
+            .get_block_state_world(below.x, below.y, below.z)
+            .and_then(|state| content.blocks.get(block_state::block_id(state)))
+            .filter(|block| block.properties.solid)
+            .map_or(BlockProperties::default().friction, |block| block.properties.friction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::content::world::test_support::{self, STONE};
+    use crate::engine::core::fixed_timestep::FixedTimestep;
+    use crate::engine::core::input::KeyState;
+
+    /// Walks forward off a ledge for two seconds of frames of `frame_time`
+    fn walk(frame_time: f32, frames: u32) -> Player {
+        let content = test_support::content();
+        let mut world = test_support::empty_world([-1, -1, -1], [1, 1, 1]);
+        for z in -1..=1 {
+            for x in -1..=1 {
+                test_support::fill_chunk(&mut world, [x, -1, z], STONE);
+            }
+        }
+        world.set_block_world(0, 0, 0, STONE);
+
+        let mut input = InputState::default();
+        input.keybind_states.insert(KeyBind::new("MoveForward"), KeyState::Held);
+
+        let mut player = Player::from_eye_position(Vec3::new(0.5, 1.0 + EYE_HEIGHT, 0.5));
+        let mut timestep = FixedTimestep::new(20, 8);
+        for _ in 0..frames {
+            for _ in 0..timestep.advance(frame_time) {
+                player.fixed_update(timestep.step(), &input, Quat::IDENTITY, &world, &content);
+            }
+        }
+        player
+    }
+
+    #[test]
+    fn movement_is_the_same_at_any_frame_rate() {
+        let slow = walk(1.0 / 20.0, 40);
+        let fast = walk(1.0 / 140.0, 280);
+
+        // walked off the block it started on, down to the floor
+        assert!(slow.position.z > 5.0);
+        assert_eq!(slow.position.y, 0.0);
+        assert!(slow.on_ground);
+
+        assert_eq!(slow.position, fast.position);
+        assert_eq!(slow.velocity, fast.velocity);
+        assert_eq!(slow.on_ground, fast.on_ground);
+    }
+}
@@ -1,2 +1,9 @@
 pub mod world;
-pub(crate) mod chunks;
\ No newline at end of file
+pub mod raycast;
+pub mod collision;
+pub mod region;
+pub mod generation;
+pub mod lighting;
+pub(crate) mod chunks;
+#[cfg(test)]
+pub(crate) mod test_support;
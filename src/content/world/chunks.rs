@@ -1,2 +1,3 @@
 pub mod chunk;
-pub mod chunk_mesh;
\ No newline at end of file
+pub mod chunk_mesh;
+pub mod chunk_workers;
\ No newline at end of file
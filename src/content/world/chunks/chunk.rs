@@ -1,4 +1,6 @@
 use crate::content::block::block::Block;
+use crate::content::block::block_state;
+use crate::content::world::lighting::{LightArray, LightChannel};
 use crate::engine::core::content_loader::GameContent;
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -6,8 +8,6 @@ use std::sync::Arc;
 /// Single chunk dimensions (16^3 because cubic)
 pub const CHUNK_SIZE: usize = 16;
 
-pub const MAX_CHUNK_INDEX: usize = 4095;
-
 /// Hardcoded ID for air (palette index 0)
 pub const AIR_BLOCK_ID: &str = "base:air";
 
@@ -15,8 +15,10 @@ pub const AIR_BLOCK_ID: &str = "base:air";
 pub struct Chunk {
     pub position: [i32; 3],
     pub blocks: Vec<u16>,                  // block palette indices
-    pub palette: Vec<String>,              // palette: index -> block ID
-    pub palette_map: HashMap<String, u16>, // block ID -> palette index
+    pub palette: Vec<String>,              // palette: index -> block state key
+    pub palette_map: HashMap<String, u16>, // block state key -> palette index
+    pub sky_light: LightArray,
+    pub block_light: LightArray,
 }
 
 impl Chunk {
@@ -33,11 +35,44 @@ impl Chunk {
             blocks: vec![0; CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE],
             palette,
             palette_map,
+            sky_light: LightArray::new(),
+            block_light: LightArray::new(),
+        }
+    }
+
+    /// Rebuilds a chunk from a palette and its palette indices, e.g. when loading from disk.
+    /// The palette has to start with air.
+    pub fn from_palette(position: [i32; 3], palette: Vec<String>, blocks: Vec<u16>) -> Self {
+        debug_assert!(palette[0] == AIR_BLOCK_ID);
+
+        let palette_map = palette
+            .iter()
+            .enumerate()
+            .map(|(i, block_id)| (block_id.clone(), i as u16))
+            .collect();
+
+        Self {
+            position,
+            blocks,
+            palette,
+            palette_map,
+            sky_light: LightArray::new(),
+            block_light: LightArray::new(),
+        }
+    }
+
+    pub fn light(&self, channel: LightChannel) -> &LightArray {
+        match channel {
+            LightChannel::Sky => &self.sky_light,
+            LightChannel::Block => &self.block_light,
         }
     }
 
-    pub fn is_air_at_idx(&self, idx: usize) -> bool {
-        self.blocks[idx] == 0 // direct index check
+    pub fn light_mut(&mut self, channel: LightChannel) -> &mut LightArray {
+        match channel {
+            LightChannel::Sky => &mut self.sky_light,
+            LightChannel::Block => &mut self.block_light,
+        }
     }
 
     #[inline(always)]
@@ -45,7 +80,7 @@ impl Chunk {
         x + y * CHUNK_SIZE + z * CHUNK_SIZE * CHUNK_SIZE
     }
 
-    /// Sets a block by ID (lookup in the palette)
+    /// Sets a block by state key, e.g. `template:log[axis=x]` (lookup in the palette)
     pub fn set_block(&mut self, x: usize, y: usize, z: usize, block_id: &str) {
         let idx = Self::index(x, y, z);
 
@@ -67,6 +102,13 @@ impl Chunk {
         self.blocks[idx] = palette_idx;
     }
 
+    /// Gets the state key of the block at the given local coordinates without going through
+    /// the registry
+    pub fn get_block_state(&self, x: usize, y: usize, z: usize) -> &str {
+        let palette_idx = self.blocks[Self::index(x, y, z)] as usize;
+        &self.palette[palette_idx]
+    }
+
     /// Gets a block from the registry with palette index
     pub fn get_block<'a>(
         &self,
@@ -77,13 +119,13 @@ impl Chunk {
     ) -> Option<Arc<Block>> {
         let idx = Self::index(x, y, z);
         let palette_idx = self.blocks[idx] as usize;
-        let block_id = self.palette.get(palette_idx)?;
-        content.blocks.get(block_id)
+        let state_key = self.palette.get(palette_idx)?;
+        content.blocks.get(block_state::block_id(state_key))
     }
 
     /// Simple halved chunk generation using blocks from the registry
     /// bottom half is dirt, top half is air
-    pub fn generate_flat(&mut self, dirt_id: &str, _content: &GameContent) {
+    pub fn generate_flat(&mut self, dirt_id: &str) {
         for x in 0..CHUNK_SIZE {
             for y in 0..CHUNK_SIZE {
                 for z in 0..CHUNK_SIZE {
@@ -99,7 +141,7 @@ impl Chunk {
         }
     }
 
-    pub fn generate_full(&mut self, dirt_id: &str, _content: &GameContent) {
+    pub fn generate_full(&mut self, dirt_id: &str) {
         for x in 0..CHUNK_SIZE {
             for y in 0..CHUNK_SIZE {
                 for z in 0..CHUNK_SIZE {
@@ -109,7 +151,7 @@ impl Chunk {
         }
     }
 
-    pub fn generate_empty(&mut self, _content: &GameContent) {
+    pub fn generate_empty(&mut self) {
         for x in 0..CHUNK_SIZE {
             for y in 0..CHUNK_SIZE {
                 for z in 0..CHUNK_SIZE {
@@ -3,127 +3,1053 @@ use std::sync::Arc;
 use vulkano::buffer::{Buffer, BufferCreateInfo, BufferUsage, Subbuffer};
 use vulkano::memory::allocator::{AllocationCreateInfo, MemoryTypeFilter, StandardMemoryAllocator};
 
-use crate::content::world::chunks::chunk::{CHUNK_SIZE, Chunk, MAX_CHUNK_INDEX};
+use crate::content::world::chunks::chunk::{AIR_BLOCK_ID, CHUNK_SIZE, Chunk};
+use crate::content::world::lighting::MAX_LIGHT;
+use crate::engine::rendering::baked_model::{BakedGeometry, BakedModel};
+use crate::engine::rendering::mesh_arena::{ArenaAllocation, MeshArena};
 use crate::engine::rendering::cube;
-use crate::engine::rendering::texture_atlas::AtlasUV;
-use crate::engine::rendering::vertex::BlockVertex;
+use crate::content::block::block::{BlockFace, RenderLayer};
+use crate::engine::rendering::texture_atlas::FaceUV;
+use crate::engine::rendering::vertex::{BlockVertex, ChunkVertex};
 use crate::utils::math::Vec3;
 
-/// Builds chunk mesh by adding one cube for each non-air block.
+/// How a chunk's exposed block faces are turned into quads.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MeshingMode {
+    /// One quad per exposed block face. At lower levels of detail every cell counts as one
+    /// block, so its faces are a quad each too.
+    Naive,
+    /// Merges coplanar faces that share an atlas entry into larger quads with tiling UVs.
+    #[default]
+    Greedy,
+}
+
+/// Settings for building chunk meshes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MeshingOptions {
+    pub mode: MeshingMode,
+    /// Darkens the corners of block faces next to other blocks
+    pub ambient_occlusion: bool,
+    /// Level of detail, 0 is full detail. Level `n` meshes the chunk in cells of `1 << n` blocks
+    /// per side, see `downsample`.
+    pub lod: u32,
+}
+
+/// Coarsest level of detail, cells of 4 blocks per side
+pub const MAX_LOD: u32 = 2;
+
+/// How far skirts reach below the surface edge they hang from, in blocks. Enough to get below
+/// the surface of a neighbor at any level of detail.
+const SKIRT_DEPTH: usize = 1 << MAX_LOD;
+
+/// Brightness of a face corner for each ambient occlusion level, from fully enclosed to open
+const AO_BRIGHTNESS: [f32; 4] = [0.45, 0.65, 0.82, 1.0];
+
+/// How the four corners of every quad in a chunk mesh are split into two triangles
+const QUAD_INDICES: [u32; 6] = [0, 1, 2, 2, 3, 0];
+
+/// Light and ambient occlusion of a block face. Greedy meshing only merges faces with the same
+/// shade, so it applies to every block a merged quad covers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct FaceShade {
+    /// Sky and block light level in front of the face
+    light: [u8; 2],
+    /// Ambient occlusion level (0 to 3, 3 is open) of each of the face's `cube::QUAD_CORNERS`
+    ao: [u8; 4],
+}
+
+/// Describes one of the six cube faces and how it maps onto the chunk axes.
+struct FaceDir {
+    template: [BlockVertex; 6],
+    /// Which of the block's face textures this face uses
+    face: BlockFace,
+    /// Axis the face points along (0 = x, 1 = y, 2 = z)
+    axis: usize,
+    /// +1 if the face points towards the positive end of `axis`, -1 otherwise
+    sign: i32,
+    /// Which position axis each texture coordinate follows, used to tile merged quads
+    uv_axes: [usize; 2],
+}
+
+// Right/left are the -x/+x faces, matching the old per-block neighbor checks.
+// Same order as `BlockFace`, so `FACES[face as usize]` is the entry of `face`.
+const FACES: [FaceDir; 6] = [
+    FaceDir { template: cube::TOP_FACE, face: BlockFace::Top, axis: 1, sign: 1, uv_axes: [0, 2] },
+    FaceDir { template: cube::BOTTOM_FACE, face: BlockFace::Bottom, axis: 1, sign: -1, uv_axes: [0, 2] },
+    FaceDir { template: cube::REAR_FACE, face: BlockFace::North, axis: 2, sign: -1, uv_axes: [0, 1] },
+    FaceDir { template: cube::FRONT_FACE, face: BlockFace::South, axis: 2, sign: 1, uv_axes: [0, 1] },
+    FaceDir { template: cube::RIGHT_FACE, face: BlockFace::West, axis: 0, sign: -1, uv_axes: [2, 1] },
+    FaceDir { template: cube::LEFT_FACE, face: BlockFace::East, axis: 0, sign: 1, uv_axes: [2, 1] },
+];
+
+/// The six face-adjacent chunks of a chunk being meshed, used to cull faces on the chunk border.
+/// A missing neighbor (not generated yet) leaves the faces towards it exposed.
+#[derive(Clone, Default)]
+pub struct ChunkNeighbors {
+    pub chunks: [Option<Arc<Chunk>>; 6],
+    /// Level of detail each neighbor is meshed at. Faces towards a neighbor are culled against
+    /// what it draws at that level, and the border to a neighbor at another level gets skirts.
+    pub lods: [u32; 6],
+}
+
+impl ChunkNeighbors {
+    /// Chunk offsets of each neighbor slot: +y, -y, -z, +z, -x, +x
+    pub const OFFSETS: [[i32; 3]; 6] = [
+        [0, 1, 0],
+        [0, -1, 0],
+        [0, 0, -1],
+        [0, 0, 1],
+        [-1, 0, 0],
+        [1, 0, 0],
+    ];
+}
+
+static MISSING_MODEL: BakedModel = BakedModel::MISSING;
+static EMPTY_MODEL: BakedModel = BakedModel::EMPTY;
+
+/// Baked models of the palette entries of a chunk and its neighbors, looked up once per mesh
+/// build instead of per block
+struct PaletteModels<'a> {
+    chunk: Vec<&'a BakedModel>,
+    /// Same slots as `ChunkNeighbors`
+    neighbors: [Option<Vec<&'a BakedModel>>; 6],
+}
+
+impl<'a> PaletteModels<'a> {
+    fn new(
+        chunk: &Chunk,
+        neighbors: &ChunkNeighbors,
+        block_models: &'a RapidHashMap<String, BakedModel>,
+    ) -> Self {
+        let resolve = |chunk: &Chunk| -> Vec<&'a BakedModel> {
+            chunk
+                .palette
+                .iter()
+                .map(|key| {
+                    if key == AIR_BLOCK_ID {
+                        &EMPTY_MODEL
+                    } else {
+                        block_models.get(key).unwrap_or(&MISSING_MODEL)
+                    }
+                })
+                .collect()
+        };
+
+        Self {
+            chunk: resolve(chunk),
+            neighbors: std::array::from_fn(|i| neighbors.chunks[i].as_deref().map(resolve)),
+        }
+    }
+
+    fn at(&self, chunk: &Chunk, idx: usize) -> &'a BakedModel {
+        self.chunk[chunk.blocks[idx] as usize]
+    }
+}
+
+/// Vertices of a chunk, four per quad, one list per render layer (indexed by `RenderLayer`)
+pub type LayerVertices = [Vec<ChunkVertex>; RenderLayer::COUNT];
+
+/// GPU side mesh of a chunk, split by render layer since each layer has its own pipeline.
+/// Its vertices live in the `MeshArena`, and have to be given back with `free`.
+pub struct ChunkMesh {
+    layers: [Option<ArenaAllocation>; RenderLayer::COUNT],
+}
+
+impl ChunkMesh {
+    /// Arena range of one render layer, `None` if the chunk has no faces in it.
+    /// Drawn with the indices of `QuadIndices`.
+    pub fn layer(&self, layer: RenderLayer) -> Option<&ArenaAllocation> {
+        self.layers[layer as usize].as_ref()
+    }
+
+    /// Most quads in any of the mesh's layers
+    pub fn max_quads(&self) -> usize {
+        self.layers
+            .iter()
+            .flatten()
+            .map(|allocation| allocation.vertices().len() as usize / 4)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Gives the mesh's vertex ranges back to the arena
+    pub fn free(self, arena: &mut MeshArena) {
+        for allocation in self.layers.into_iter().flatten() {
+            arena.free(allocation);
+        }
+    }
+}
+
+/// Builds chunk mesh with the given meshing options and uploads it to the mesh arena.
+/// Returns `None` if the chunk has no visible faces.
 pub fn build_chunk_mesh(
-    allocator: Arc<StandardMemoryAllocator>,
+    arena: &mut MeshArena,
+    chunk: &Chunk,
+    neighbors: &ChunkNeighbors,
+    block_models: &RapidHashMap<String, BakedModel>,
+    options: MeshingOptions,
+) -> Option<ChunkMesh> {
+    let vertices = build_chunk_vertices(chunk, neighbors, block_models, options);
+    upload_chunk_vertices(arena, vertices)
+}
+
+/// Uploads vertices built by `build_chunk_vertices` to the mesh arena.
+/// Returns `None` if there are no vertices in any layer.
+pub fn upload_chunk_vertices(
+    arena: &mut MeshArena,
+    vertices: LayerVertices,
+) -> Option<ChunkMesh> {
+    if vertices.iter().all(Vec::is_empty) {
+        return None;
+    }
+
+    Some(ChunkMesh {
+        layers: vertices.map(|vertices| {
+            (!vertices.is_empty()).then(|| arena.upload(&vertices))
+        }),
+    })
+}
+
+/// Builds the CPU side vertex lists for a chunk. Doesn't touch the GPU, so vertex counts of the
+/// different meshing modes can be compared directly.
+pub fn build_chunk_vertices(
     chunk: &Chunk,
-    block_uvs: &RapidHashMap<String, AtlasUV>,
-) -> Option<Subbuffer<[BlockVertex]>> {
+    neighbors: &ChunkNeighbors,
+    block_models: &RapidHashMap<String, BakedModel>,
+    options: MeshingOptions,
+) -> LayerVertices {
+    let mut vertices = LayerVertices::default();
     if chunk.palette.len() == 1 {
-        return None; // early return if only air is in the palette
+        return vertices; // early return if only air is in the palette
     }
-    let mut vertices = Vec::new();
 
-    let map_uv = |orig: [f32; 2], atlas: AtlasUV| -> [f32; 2] {
-        [
-            atlas.uv_min[0] + orig[0] * (atlas.uv_max[0] - atlas.uv_min[0]),
-            atlas.uv_min[1] + orig[1] * (atlas.uv_max[1] - atlas.uv_min[1]),
-        ]
+    let models = PaletteModels::new(chunk, neighbors, block_models);
+
+    // Lower levels of detail mesh downsampled copies instead. Neighbors are downsampled to the
+    // level they're meshed at, so faces towards them are culled against what's actually drawn
+    // there and the border has no holes. Downsampling keeps the palette, so `models` still fits.
+    let lod_chunk = (options.lod > 0).then(|| downsample(chunk, options.lod));
+    let chunk = lod_chunk.as_ref().unwrap_or(chunk);
+    let neighbors = &ChunkNeighbors {
+        chunks: std::array::from_fn(|slot| {
+            let neighbor = neighbors.chunks[slot].as_ref()?;
+            Some(match neighbors.lods[slot] {
+                0 => neighbor.clone(),
+                lod => Arc::new(downsample(neighbor, lod)),
+            })
+        }),
+        lods: neighbors.lods,
     };
 
-    let is_air = |idx: usize| chunk.is_air_at_idx(idx);
+    let ao = options.ambient_occlusion;
+    let cell = 1 << options.lod;
+    match options.mode {
+        MeshingMode::Naive => build_naive(&mut vertices, chunk, neighbors, &models, cell, ao),
+        MeshingMode::Greedy => build_greedy(&mut vertices, chunk, neighbors, &models, ao),
+    }
+    push_model_quads(&mut vertices, chunk, neighbors, &models, cell);
+    push_skirts(&mut vertices, chunk, neighbors, &models, options.lod);
+    vertices
+}
+
+/// Copy of `chunk` at a lower level of detail. Every cell of `1 << lod` blocks per side is
+/// filled with its most common non-air block, or with air if air makes up more than half of it.
+/// Slabs, stairs and plants count like any other block, so places made of them keep them.
+/// Each cell takes the brightest light in it, so surfaces that move into a cell aren't dark.
+fn downsample(chunk: &Chunk, lod: u32) -> Chunk {
+    let cell = 1 << lod;
+    let cell_blocks = cell * cell * cell;
+    let mut lod_chunk = chunk.clone();
+    let mut counts = vec![0usize; chunk.palette.len()];
+
+    for cell_z in (0..CHUNK_SIZE).step_by(cell) {
+        for cell_y in (0..CHUNK_SIZE).step_by(cell) {
+            for cell_x in (0..CHUNK_SIZE).step_by(cell) {
+                let cell_indices = (0..cell_blocks).map(|i| {
+                    let (x, y, z) = (i % cell, (i / cell) % cell, i / (cell * cell));
+                    Chunk::index(cell_x + x, cell_y + y, cell_z + z)
+                });
+
+                counts.fill(0);
+                let mut light = [0; 2];
+                for idx in cell_indices.clone() {
+                    counts[chunk.blocks[idx] as usize] += 1;
+                    light[0] = light[0].max(chunk.sky_light.get(idx));
+                    light[1] = light[1].max(chunk.block_light.get(idx));
+                }
+
+                // palette index 0 is always air
+                let fill = if counts[0] * 2 <= cell_blocks {
+                    (1..counts.len()).max_by_key(|&i| counts[i]).unwrap_or(0) as u16
+                } else {
+                    0
+                };
+
+                for idx in cell_indices {
+                    lod_chunk.blocks[idx] = fill;
+                    lod_chunk.sky_light.set(idx, light[0]);
+                    lod_chunk.block_light.set(idx, light[1]);
+                }
+            }
+        }
+    }
+    lod_chunk
+}
+
+/// Adds one cube for each full cube block, skipping faces hidden by their neighbor.
+/// A downsampled chunk is filled in whole cells of `cell` blocks per side, so each cell is
+/// added as one big cube instead of a cube per block.
+fn build_naive(
+    vertices: &mut LayerVertices,
+    chunk: &Chunk,
+    neighbors: &ChunkNeighbors,
+    models: &PaletteModels,
+    cell: usize,
+    ambient_occlusion: bool,
+) {
+    for z in (0..CHUNK_SIZE).step_by(cell) {
+        for y in (0..CHUNK_SIZE).step_by(cell) {
+            for x in (0..CHUNK_SIZE).step_by(cell) {
+                let model = models.at(chunk, Chunk::index(x, y, z));
+                let BakedGeometry::Cube(block_uvs) = &model.geometry else {
+                    continue;
+                };
+                let layer = &mut vertices[model.render_layer as usize];
+                let pos = [x, y, z];
+
+                // conditionally adding vertices if nothing in front of them hides them
+                for face in &FACES {
+                    if is_cell_face_exposed(chunk, neighbors, models, pos, cell, face) {
+                        let atlas = block_uvs.face(face.face);
+                        let shade = cell_face_shade(
+                            chunk,
+                            neighbors,
+                            models,
+                            pos,
+                            cell,
+                            face,
+                            ambient_occlusion,
+                        );
+                        push_face(layer, face, pos, [cell; 3], atlas, shade);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Sweeps each face direction slice by slice, growing quads first along one plane axis and then
+/// the other while the faces keep sharing the same atlas entry, render layer and shade.
+/// Only full cubes get merged.
+fn build_greedy(
+    vertices: &mut LayerVertices,
+    chunk: &Chunk,
+    neighbors: &ChunkNeighbors,
+    models: &PaletteModels,
+    ambient_occlusion: bool,
+) {
+    let mut mask: [Option<(FaceUV, RenderLayer, FaceShade)>; CHUNK_SIZE * CHUNK_SIZE] =
+        [None; CHUNK_SIZE * CHUNK_SIZE];
+
+    for face in &FACES {
+        // the two axes spanning the face plane
+        let u_axis = (face.axis + 1) % 3;
+        let v_axis = (face.axis + 2) % 3;
+
+        for slice in 0..CHUNK_SIZE {
+            // mark every exposed face in this slice with its atlas entry and shade
+            for v in 0..CHUNK_SIZE {
+                for u in 0..CHUNK_SIZE {
+                    let mut pos = [0; 3];
+                    pos[face.axis] = slice;
+                    pos[u_axis] = u;
+                    pos[v_axis] = v;
+
+                    let idx = Chunk::index(pos[0], pos[1], pos[2]);
+                    let model = models.at(chunk, idx);
+                    mask[u + v * CHUNK_SIZE] = match &model.geometry {
+                        BakedGeometry::Cube(block_uvs)
+                            if is_face_exposed(chunk, neighbors, models, pos, face) =>
+                        {
+                            Some((
+                                block_uvs.face(face.face),
+                                model.render_layer,
+                                face_shade(chunk, neighbors, models, pos, face, ambient_occlusion),
+                            ))
+                        }
+                        _ => None,
+                    };
+                }
+            }
+
+            // merge the marked faces into as few quads as possible
+            for v in 0..CHUNK_SIZE {
+                let mut u = 0;
+                while u < CHUNK_SIZE {
+                    let Some(entry) = mask[u + v * CHUNK_SIZE] else {
+                        u += 1;
+                        continue;
+                    };
+
+                    let mut width = 1;
+                    while u + width < CHUNK_SIZE && mask[u + width + v * CHUNK_SIZE] == Some(entry)
+                    {
+                        width += 1;
+                    }
+
+                    let mut height = 1;
+                    'grow: while v + height < CHUNK_SIZE {
+                        for du in 0..width {
+                            if mask[u + du + (v + height) * CHUNK_SIZE] != Some(entry) {
+                                break 'grow;
+                            }
+                        }
+                        height += 1;
+                    }
+
+                    for dv in 0..height {
+                        for du in 0..width {
+                            mask[u + du + (v + dv) * CHUNK_SIZE] = None;
+                        }
+                    }
+
+                    let mut pos = [0; 3];
+                    pos[face.axis] = slice;
+                    pos[u_axis] = u;
+                    pos[v_axis] = v;
 
-    for idx in 0..MAX_CHUNK_INDEX + 1 {
-        let palette_idx = chunk.blocks[idx] as usize;
-        let block_id = &chunk.palette[palette_idx];
+                    let mut size = [1; 3];
+                    size[u_axis] = width;
+                    size[v_axis] = height;
 
-        if chunk.is_air_at_idx(idx) {
+                    let (atlas, layer, shade) = entry;
+                    let layer = &mut vertices[layer as usize];
+                    push_face(layer, face, pos, size, atlas, shade);
+                    u += width;
+                }
+            }
+        }
+    }
+}
+
+/// Adds the quads of every block that isn't a full cube, leaving out quads whose cullface is
+/// hidden by the neighbor on that side. A downsampled chunk gets one model per cell of `cell`
+/// blocks per side, scaled up to fill the cell.
+fn push_model_quads(
+    vertices: &mut LayerVertices,
+    chunk: &Chunk,
+    neighbors: &ChunkNeighbors,
+    models: &PaletteModels,
+    cell: usize,
+) {
+    for z in (0..CHUNK_SIZE).step_by(cell) {
+        for y in (0..CHUNK_SIZE).step_by(cell) {
+            for x in (0..CHUNK_SIZE).step_by(cell) {
+                let idx = Chunk::index(x, y, z);
+                let model = models.at(chunk, idx);
+                let BakedGeometry::Quads(quads) = &model.geometry else {
+                    continue;
+                };
+                if quads.is_empty() {
+                    continue;
+                }
+
+                let pos = [x, y, z];
+                let block_origin = Vec3::from([x as f32, y as f32, z as f32]);
+
+                for quad in quads {
+                    let cull_face = quad.cullface.map(|cullface| &FACES[cullface as usize]);
+                    let visible = cull_face.is_none_or(|face| {
+                        is_cell_face_exposed(chunk, neighbors, models, pos, cell, face)
+                    });
+                    if !visible {
+                        continue;
+                    }
+
+                    // quads on the side of the block are lit like a cube face, the rest by the
+                    // block itself
+                    let light = match cull_face {
+                        Some(face) => {
+                            let edge = cell_block(pos, cell, face, Vec3::default());
+                            face_light(chunk, neighbors, edge, face)
+                        }
+                        None => [chunk.sky_light.get(idx), chunk.block_light.get(idx)],
+                    };
+                    vertices[model.render_layer as usize].extend(quad.vertices.map(|v| {
+                        let position = block_origin + v.position * cell as f32;
+                        let (uv, rect) = (v.tex_coords, v.atlas_rect);
+                        ChunkVertex::pack(position.0, uv, rect, light, MAX_LIGHT, 1.0)
+                    }));
+                }
+            }
+        }
+    }
+}
+
+/// Hangs a skirt from every surface edge along the border to a neighbor at another level of
+/// detail. Faces on both sides are culled against what the other side draws, so the border has
+/// no holes, but the coarse side's quads end where the fine side's don't, and those T-junctions
+/// leave cracks a pixel wide. Skirts sit one position step behind the border and reach
+/// `SKIRT_DEPTH` blocks down, past the surface of the neighbor, so faces on the border are drawn
+/// over them and the cracks show a skirt instead of whatever is behind the terrain.
+fn push_skirts(
+    vertices: &mut LayerVertices,
+    chunk: &Chunk,
+    neighbors: &ChunkNeighbors,
+    models: &PaletteModels,
+    lod: u32,
+) {
+    let cell = 1 << lod;
+
+    // only the four sides, top and bottom don't run along the surface
+    for (slot, face) in FACES.iter().enumerate().skip(2) {
+        if neighbors.lods[slot] == lod || neighbors.chunks[slot].is_none() {
             continue;
         }
+        let along = 2 - face.axis; // the horizontal axis running along the border
 
-        // deriving the coords instead of nested loops
-        let x = idx % CHUNK_SIZE;
-        let y = (idx / CHUNK_SIZE) % CHUNK_SIZE;
-        let z = idx / (CHUNK_SIZE * CHUNK_SIZE);
-
-        // Precompute neighbor "air" checks
-        let top_air = y == CHUNK_SIZE - 1 || is_air(idx + CHUNK_SIZE);
-        let bottom_air = y == 0 || is_air(idx - CHUNK_SIZE);
-        let rear_air = z == 0 || is_air(idx - CHUNK_SIZE * CHUNK_SIZE);
-        let front_air = z == CHUNK_SIZE - 1 || is_air(idx + CHUNK_SIZE * CHUNK_SIZE);
-        let right_air = x == 0 || is_air(idx - 1);
-        let left_air = x == CHUNK_SIZE - 1 || is_air(idx + 1);
-
-        let atlas = block_uvs.get(block_id).copied().unwrap_or(AtlasUV {
-            uv_min: [0.0, 0.0],
-            uv_max: [1.0, 1.0],
-        });
+        for u in (0..CHUNK_SIZE).step_by(cell) {
+            for y in (0..CHUNK_SIZE).step_by(cell) {
+                let mut pos = [0; 3];
+                pos[along] = u;
+                pos[1] = y;
+                let edge = cell_block(pos, cell, face, Vec3::default());
+                let model = models.at(chunk, Chunk::index(edge[0], edge[1], edge[2]));
+                let BakedGeometry::Cube(block_uvs) = &model.geometry else {
+                    continue;
+                };
+
+                // surface edges have something other than a full cube above them
+                let mut above = edge.map(|c| c as i32);
+                above[1] = (y + cell) as i32;
+                let covered = model_at(chunk, neighbors, models, above)
+                    .is_some_and(|above| matches!(above.geometry, BakedGeometry::Cube(_)));
+                if covered {
+                    continue;
+                }
+
+                // lit like the open space above the edge
+                let light = match block_at(chunk, neighbors, above) {
+                    Some((above_chunk, idx, _)) => {
+                        [above_chunk.sky_light.get(idx), above_chunk.block_light.get(idx)]
+                    }
+                    None => [MAX_LIGHT, 0],
+                };
+                let shade = FaceShade { light, ao: [3; 4] };
+
+                let top = y + cell;
+                let bottom = top.saturating_sub(SKIRT_DEPTH.max(cell));
+                let mut size = [cell; 3];
+                size[1] = top - bottom;
+                let mut skirt_pos = edge;
+                skirt_pos[1] = bottom;
+
+                let layer = &mut vertices[model.render_layer as usize];
+                let start = layer.len();
+                push_face(layer, face, skirt_pos, size, block_uvs.face(face.face), shade);
+                for vertex in &mut layer[start..] {
+                    let border = vertex.position[face.axis] as i32;
+                    vertex.position[face.axis] = (border - face.sign) as u16;
+                }
+            }
+        }
+    }
+}
+
+/// Block of a cell of `cell` blocks per side starting at `pos` that lies against `face`, in the
+/// direction of the face `corner` (a template position) along the other two axes
+#[inline(always)]
+fn cell_block(pos: [usize; 3], cell: usize, face: &FaceDir, corner: Vec3) -> [usize; 3] {
+    [0, 1, 2].map(|axis| {
+        let far = if axis == face.axis { face.sign > 0 } else { corner.0[axis] > 0.0 };
+        if far { pos[axis] + cell - 1 } else { pos[axis] }
+    })
+}
 
-        // if this ever fails, math has somehow broken.
-        debug_assert!(Chunk::index(x, y, z) == idx);
+/// `is_face_exposed` for the face of a whole cell, `cell` blocks per side starting at `pos`.
+/// Cells inside a downsampled chunk are uniform, but a neighbor at a finer level of detail isn't,
+/// so on the chunk border the face is exposed if any block in front of it leaves it visible.
+#[inline(always)]
+fn is_cell_face_exposed(
+    chunk: &Chunk,
+    neighbors: &ChunkNeighbors,
+    models: &PaletteModels,
+    pos: [usize; 3],
+    cell: usize,
+    face: &FaceDir,
+) -> bool {
+    let edge = cell_block(pos, cell, face, Vec3::default());
+    let front = edge[face.axis] as i32 + face.sign;
+    if cell == 1 || (0..CHUNK_SIZE as i32).contains(&front) {
+        return is_face_exposed(chunk, neighbors, models, edge, face);
+    }
+
+    let (u_axis, v_axis) = ((face.axis + 1) % 3, (face.axis + 2) % 3);
+    (0..cell * cell).any(|i| {
+        let mut block = edge;
+        block[u_axis] += i % cell;
+        block[v_axis] += i / cell;
+        is_face_exposed(chunk, neighbors, models, block, face)
+    })
+}
+
+/// Checks whether the block in front of `face` leaves it visible, i.e. doesn't completely cover
+/// the side touching it. Two full cubes of the same see-through state (like glass next to glass)
+/// hide each other's touching faces too, so they look like one volume.
+/// Faces on the chunk border look into the neighboring chunk, and count as exposed if that chunk
+/// isn't available.
+#[inline(always)]
+fn is_face_exposed(
+    chunk: &Chunk,
+    neighbors: &ChunkNeighbors,
+    models: &PaletteModels,
+    pos: [usize; 3],
+    face: &FaceDir,
+) -> bool {
+    let Some(neighbor) = neighbor_model(chunk, neighbors, models, pos, face) else {
+        return true;
+    };
+    if neighbor.occludes[face.face.opposite() as usize] {
+        return false;
+    }
+
+    let model = models.at(chunk, Chunk::index(pos[0], pos[1], pos[2]));
+    !(std::ptr::eq(model, neighbor) && matches!(model.geometry, BakedGeometry::Cube(_)))
+}
+
+/// Model of the block in front of `face`, `None` if it's in a neighboring chunk that isn't
+/// available
+#[inline(always)]
+fn neighbor_model<'a>(
+    chunk: &Chunk,
+    neighbors: &ChunkNeighbors,
+    models: &PaletteModels<'a>,
+    pos: [usize; 3],
+    face: &FaceDir,
+) -> Option<&'a BakedModel> {
+    let mut neighbor = pos.map(|c| c as i32);
+    neighbor[face.axis] += face.sign;
+    model_at(chunk, neighbors, models, neighbor)
+}
+
+/// Model of the block at a chunk local position, which may be up to one block outside the
+/// chunk. `None` if that block isn't available.
+#[inline(always)]
+fn model_at<'a>(
+    chunk: &Chunk,
+    neighbors: &ChunkNeighbors,
+    models: &PaletteModels<'a>,
+    pos: [i32; 3],
+) -> Option<&'a BakedModel> {
+    let (block_chunk, idx, slot) = block_at(chunk, neighbors, pos)?;
+    match slot {
+        Some(slot) => {
+            let neighbor_models = models.neighbors[slot].as_ref()?;
+            Some(neighbor_models[block_chunk.blocks[idx] as usize])
+        }
+        None => Some(models.at(chunk, idx)),
+    }
+}
+
+/// `face_shade` of the face of a whole cell, `cell` blocks per side starting at `pos`.
+/// Each corner gets the ambient occlusion of the block in that corner of the face.
+#[inline(always)]
+fn cell_face_shade(
+    chunk: &Chunk,
+    neighbors: &ChunkNeighbors,
+    models: &PaletteModels,
+    pos: [usize; 3],
+    cell: usize,
+    face: &FaceDir,
+    ambient_occlusion: bool,
+) -> FaceShade {
+    if cell == 1 {
+        return face_shade(chunk, neighbors, models, pos, face, ambient_occlusion);
+    }
+
+    let ao = if ambient_occlusion {
+        cube::QUAD_CORNERS.map(|corner| {
+            let corner = face.template[corner].position;
+            let block = cell_block(pos, cell, face, corner);
+            corner_ao(chunk, neighbors, models, block, face, corner)
+        })
+    } else {
+        [3; 4]
+    };
+
+    FaceShade {
+        light: face_light(chunk, neighbors, cell_block(pos, cell, face, Vec3::default()), face),
+        ao,
+    }
+}
+
+/// Light and ambient occlusion of `face`. Without ambient occlusion every corner is open.
+#[inline(always)]
+fn face_shade(
+    chunk: &Chunk,
+    neighbors: &ChunkNeighbors,
+    models: &PaletteModels,
+    pos: [usize; 3],
+    face: &FaceDir,
+    ambient_occlusion: bool,
+) -> FaceShade {
+    let ao = if ambient_occlusion {
+        cube::QUAD_CORNERS.map(|corner| {
+            corner_ao(chunk, neighbors, models, pos, face, face.template[corner].position)
+        })
+    } else {
+        [3; 4]
+    };
+
+    FaceShade {
+        light: face_light(chunk, neighbors, pos, face),
+        ao,
+    }
+}
+
+/// Ambient occlusion level of one face corner (template position), from the two blocks
+/// beside the corner and the one diagonal to it in the layer in front of the face.
+/// Two sides fully enclose the corner no matter what's on the diagonal.
+#[inline(always)]
+fn corner_ao(
+    chunk: &Chunk,
+    neighbors: &ChunkNeighbors,
+    models: &PaletteModels,
+    pos: [usize; 3],
+    face: &FaceDir,
+    corner: Vec3,
+) -> u8 {
+    let u_axis = (face.axis + 1) % 3;
+    let v_axis = (face.axis + 2) % 3;
+    let step = |axis: usize| if corner.0[axis] > 0.0 { 1 } else { -1 };
+
+    let mut front = pos.map(|c| c as i32);
+    front[face.axis] += face.sign;
+    let mut side_u = front;
+    side_u[u_axis] += step(u_axis);
+    let mut side_v = front;
+    side_v[v_axis] += step(v_axis);
+    let mut diagonal = side_u;
+    diagonal[v_axis] = side_v[v_axis];
+
+    let occludes = |pos: [i32; 3]| {
+        model_at(chunk, neighbors, models, pos).is_some_and(BakedModel::casts_ambient_occlusion)
+    };
+    let (side_u, side_v) = (occludes(side_u), occludes(side_v));
+    if side_u && side_v {
+        return 0;
+    }
+    3 - side_u as u8 - side_v as u8 - occludes(diagonal) as u8
+}
+
+/// Sky and block light shining on `face`, taken from the block in front of it.
+/// Faces looking into a missing chunk get full sky light so they don't show up black.
+#[inline(always)]
+fn face_light(chunk: &Chunk, neighbors: &ChunkNeighbors, pos: [usize; 3], face: &FaceDir) -> [u8; 2] {
+    let Some((front_chunk, idx, _)) = neighbor_block(chunk, neighbors, pos, face) else {
+        return [MAX_LIGHT, 0];
+    };
+    [front_chunk.sky_light.get(idx), front_chunk.block_light.get(idx)]
+}
+
+/// Chunk and index of the block in front of `face`, along with the `ChunkNeighbors` slot if it's
+/// in a neighboring chunk. `None` if that chunk isn't available.
+#[inline(always)]
+fn neighbor_block<'a>(
+    chunk: &'a Chunk,
+    neighbors: &'a ChunkNeighbors,
+    pos: [usize; 3],
+    face: &FaceDir,
+) -> Option<(&'a Chunk, usize, Option<usize>)> {
+    let mut neighbor = pos.map(|c| c as i32);
+    neighbor[face.axis] += face.sign;
+    block_at(chunk, neighbors, neighbor)
+}
+
+/// Chunk and index of the block at a chunk local position up to one block outside the chunk,
+/// along with the `ChunkNeighbors` slot if it's in a neighboring chunk. Only the face neighbors
+/// are available, so positions outside the chunk on more than one axis give `None`, as do
+/// neighbors that aren't loaded.
+#[inline(always)]
+fn block_at<'a>(
+    chunk: &'a Chunk,
+    neighbors: &'a ChunkNeighbors,
+    pos: [i32; 3],
+) -> Option<(&'a Chunk, usize, Option<usize>)> {
+    let size = CHUNK_SIZE as i32;
+    let offset = pos.map(|c| if c < 0 { -1 } else if c >= size { 1 } else { 0 });
+
+    // wrap around to the opposite side of the neighboring chunk
+    let local = pos.map(|c| c.rem_euclid(size) as usize);
+    let idx = Chunk::index(local[0], local[1], local[2]);
 
-        let pos_offset = Vec3::from([x as f32, y as f32, z as f32])
-            + (Vec3::from(chunk.position) * CHUNK_SIZE as f32);
+    if offset == [0; 3] {
+        return Some((chunk, idx, None));
+    }
+    let slot = ChunkNeighbors::OFFSETS.iter().position(|&o| o == offset)?;
+    Some((neighbors.chunks[slot].as_deref()?, idx, Some(slot)))
+}
+
+/// Emits one face covering `size` blocks starting at the chunk local block `pos`.
+/// Blocks occupy `[pos, pos + 1)` in chunk space, so the centred cube templates get shifted
+/// by half a block and stretched over the quad. Texture coordinates are stretched along with
+/// the quad so the texture repeats once per block. The quad is split along the diagonal whose
+/// corners are the least occluded, so the occlusion gradient doesn't depend on the triangulation.
+/// Every quad uses the same indices, so the split is picked by which corner goes first.
+fn push_face(
+    vertices: &mut Vec<ChunkVertex>,
+    face: &FaceDir,
+    pos: [usize; 3],
+    size: [usize; 3],
+    face_uv: FaceUV,
+    shade: FaceShade,
+) {
+    let atlas_rect = face_uv.atlas.rect();
+
+    // starting at the second corner splits the quad along the other diagonal
+    let flip = shade.ao[0] + shade.ao[2] < shade.ao[1] + shade.ao[3];
+    let order = if flip { [1, 2, 3, 0] } else { [0, 1, 2, 3] };
+
+    vertices.extend(order.map(|corner_idx| {
+        let v = face.template[cube::QUAD_CORNERS[corner_idx]];
+        let corner = v.position + Vec3::from([0.5, 0.5, 0.5]);
+        let position = [0, 1, 2].map(|axis| pos[axis] as f32 + corner.0[axis] * size[axis] as f32);
+        let tex_coords = face_uv.transform([
+            v.tex_coords[0] * size[face.uv_axes[0]] as f32,
+            v.tex_coords[1] * size[face.uv_axes[1]] as f32,
+        ]);
+        let ao = AO_BRIGHTNESS[shade.ao[corner_idx] as usize];
+        ChunkVertex::pack(position, tex_coords, atlas_rect, shade.light, MAX_LIGHT, ao)
+    }));
+}
+
+/// Index buffer shared by every chunk mesh. All quads are split the same way relative to their
+/// four vertices, so one list of indices works for any mesh with at most `quads` quads.
+pub struct QuadIndices {
+    buffer: Subbuffer<[u32]>,
+    quads: usize,
+}
+
+impl QuadIndices {
+    pub fn new(allocator: Arc<StandardMemoryAllocator>, quads: usize) -> Self {
+        let indices: Vec<u32> = (0..quads as u32)
+            .flat_map(|quad| QUAD_INDICES.map(|index| quad * 4 + index))
+            .collect();
+        let buffer = Buffer::from_iter(
+            allocator,
+            BufferCreateInfo {
+                usage: BufferUsage::INDEX_BUFFER,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
+                    | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+                ..Default::default()
+            },
+            indices,
+        )
+        .unwrap();
+
+        Self { buffer, quads }
+    }
 
-        // conditionally adding vertices if they are on the edge or are next to air
-        if top_air {
-            vertices.extend(cube::TOP_FACE.map(|mut v| {
-                v.position += pos_offset;
-                v.tex_coords = map_uv(v.tex_coords, atlas);
-                v
-            }));
+    /// Makes room for meshes with up to `quads` quads, reallocating the buffer if it's too small.
+    /// Command buffers still using the old buffer keep it alive until they're done.
+    pub fn reserve(&mut self, allocator: Arc<StandardMemoryAllocator>, quads: usize) {
+        if quads > self.quads {
+            *self = Self::new(allocator, quads.next_power_of_two());
         }
-        if bottom_air {
-            vertices.extend(cube::BOTTOM_FACE.map(|mut v| {
-                v.position += pos_offset;
-                v.tex_coords = map_uv(v.tex_coords, atlas);
-                v
-            }));
+    }
+
+    pub fn buffer(&self) -> &Subbuffer<[u32]> {
+        &self.buffer
+    }
+
+    /// Number of indices drawing the given number of vertices
+    pub fn index_count(vertices: u64) -> u32 {
+        (vertices / 4 * QUAD_INDICES.len() as u64) as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::rendering::baked_model::BakedQuad;
+    use crate::engine::rendering::texture_atlas::{AtlasUV, BlockFaceUVs};
+
+    const STONE: &str = "test:stone";
+    const DIRT: &str = "test:dirt";
+    const PLANT: &str = "test:plant";
+
+    /// A full cube with the same texture on every face, `tile` picks its spot in the atlas
+    fn cube_model(tile: f32) -> BakedModel {
+        let face = FaceUV {
+            atlas: AtlasUV {
+                uv_min: [tile * 0.25, 0.0],
+                uv_max: [tile * 0.25 + 0.25, 0.25],
+            },
+            tex_transform: FaceUV::IDENTITY_TRANSFORM,
+        };
+        BakedModel {
+            geometry: BakedGeometry::Cube(BlockFaceUVs { faces: [face; 6] }),
+            occludes: [true; 6],
+            render_layer: RenderLayer::Opaque,
         }
-        if rear_air {
-            vertices.extend(cube::REAR_FACE.map(|mut v| {
-                v.position += pos_offset;
-                v.tex_coords = map_uv(v.tex_coords, atlas);
-                v
-            }));
+    }
+
+    /// A single quad standing in the middle of the block, hidden by nothing
+    fn plant_model() -> BakedModel {
+        let vertex = |x: f32, y: f32| BlockVertex {
+            position: Vec3::from([x, y, 0.5]),
+            tex_coords: [x, y],
+            atlas_rect: [0.0, 0.0, 0.25, 0.25],
+        };
+        let quad = BakedQuad {
+            vertices: [vertex(0.0, 0.0), vertex(1.0, 0.0), vertex(1.0, 1.0), vertex(0.0, 1.0)],
+            cullface: None,
+        };
+        BakedModel {
+            geometry: BakedGeometry::Quads(vec![quad]),
+            occludes: [false; 6],
+            render_layer: RenderLayer::Cutout,
         }
-        if front_air {
-            vertices.extend(cube::FRONT_FACE.map(|mut v| {
-                v.position += pos_offset;
-                v.tex_coords = map_uv(v.tex_coords, atlas);
-                v
-            }));
+    }
+
+    fn block_models() -> RapidHashMap<String, BakedModel> {
+        let mut models = RapidHashMap::default();
+        models.insert(STONE.to_string(), cube_model(0.0));
+        models.insert(DIRT.to_string(), cube_model(1.0));
+        models.insert(PLANT.to_string(), plant_model());
+        models
+    }
+
+    /// Chunk with `block(x, y, z)` at every position
+    fn chunk_of(block: impl Fn(usize, usize, usize) -> &'static str) -> Chunk {
+        let mut chunk = Chunk::new([0, 0, 0]);
+        for z in 0..CHUNK_SIZE {
+            for y in 0..CHUNK_SIZE {
+                for x in 0..CHUNK_SIZE {
+                    chunk.set_block(x, y, z, block(x, y, z));
+                }
+            }
         }
-        if right_air {
-            vertices.extend(cube::RIGHT_FACE.map(|mut v| {
-                v.position += pos_offset;
-                v.tex_coords = map_uv(v.tex_coords, atlas);
-                v
-            }));
+        chunk
+    }
+
+    /// Vertices of the naive and the greedy mesh of a chunk without neighbors
+    fn vertex_counts(chunk: &Chunk, ambient_occlusion: bool) -> (usize, usize) {
+        let models = block_models();
+        let count = |mode| {
+            let options = MeshingOptions { mode, ambient_occlusion, lod: 0 };
+            build_chunk_vertices(chunk, &ChunkNeighbors::default(), &models, options)
+                .iter()
+                .map(Vec::len)
+                .sum()
+        };
+        (count(MeshingMode::Naive), count(MeshingMode::Greedy))
+    }
+
+    #[test]
+    fn solid_chunk_merges_into_one_quad_per_side() {
+        let chunk = chunk_of(|_, _, _| STONE);
+        for ambient_occlusion in [false, true] {
+            let (naive, greedy) = vertex_counts(&chunk, ambient_occlusion);
+            assert_eq!(naive, 6 * CHUNK_SIZE * CHUNK_SIZE * 4);
+            assert_eq!(greedy, 6 * 4);
         }
-        if left_air {
-            vertices.extend(cube::LEFT_FACE.map(|mut v| {
-                v.position += pos_offset;
-                v.tex_coords = map_uv(v.tex_coords, atlas);
-                v
-            }));
+    }
+
+    #[test]
+    fn checkerboard_chunk_has_nothing_to_merge() {
+        let chunk = chunk_of(|x, y, z| if (x + y + z) % 2 == 0 { STONE } else { AIR_BLOCK_ID });
+        let blocks = CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE / 2;
+        for ambient_occlusion in [false, true] {
+            let (naive, greedy) = vertex_counts(&chunk, ambient_occlusion);
+            assert_eq!(naive, blocks * 6 * 4);
+            assert!(greedy <= naive);
         }
     }
 
-    if !vertices.is_empty() {
-        Some(
-            Buffer::from_iter(
-                allocator.clone(),
-                BufferCreateInfo {
-                    usage: BufferUsage::VERTEX_BUFFER,
-                    ..Default::default()
-                },
-                AllocationCreateInfo {
-                    memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
-                        | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
-                    ..Default::default()
-                },
-                vertices,
-            )
-            .unwrap(),
-        )
-    } else {
-        None
+    #[test]
+    fn mixed_textures_only_merge_with_their_own_texture() {
+        // stripes one block wide along x, alternating between the two textures
+        let chunk = chunk_of(|x, _, _| if x % 2 == 0 { STONE } else { DIRT });
+        let (naive, greedy) = vertex_counts(&chunk, false);
+        assert_eq!(naive, 6 * CHUNK_SIZE * CHUNK_SIZE * 4);
+        // one quad per stripe on the four sides crossing the stripes, one each on the ends
+        assert_eq!(greedy, (4 * CHUNK_SIZE + 2) * 4);
+        assert!(greedy <= naive);
+    }
+
+    #[test]
+    fn empty_chunk_has_no_vertices() {
+        assert_eq!(vertex_counts(&Chunk::new([0, 0, 0]), true), (0, 0));
+    }
+
+    #[test]
+    fn downsampling_keeps_the_most_common_block() {
+        // every 2x2x2 cell is half air, three eighths stone and one eighth dirt
+        let chunk = chunk_of(|x, y, z| match (x % 2, y % 2, z % 2) {
+            (0, _, _) => AIR_BLOCK_ID,
+            (_, 0, 0) => DIRT,
+            _ => STONE,
+        });
+        let lod_chunk = downsample(&chunk, 1);
+        assert_eq!(lod_chunk.get_block_state(0, 0, 0), STONE);
+        assert_eq!(lod_chunk.get_block_state(1, 1, 1), STONE);
+
+        // plants count like any other block
+        let chunk = chunk_of(|x, _, _| if x % 2 == 0 { PLANT } else { AIR_BLOCK_ID });
+        assert_eq!(downsample(&chunk, 1).get_block_state(1, 0, 0), PLANT);
+
+        // less than half of the cell is solid
+        let chunk = chunk_of(|x, y, _| if x % 2 == 0 && y % 2 == 0 { STONE } else { AIR_BLOCK_ID });
+        assert_eq!(downsample(&chunk, 1).get_block_state(0, 0, 0), AIR_BLOCK_ID);
+    }
+
+    #[test]
+    fn naive_meshing_merges_cells_at_lower_detail() {
+        let models = block_models();
+        let chunk = chunk_of(|_, _, _| STONE);
+        let count = |neighbors: &ChunkNeighbors| {
+            let options =
+                MeshingOptions { mode: MeshingMode::Naive, ambient_occlusion: true, lod: 1 };
+            build_chunk_vertices(&chunk, neighbors, &models, options)
+                .iter()
+                .map(Vec::len)
+                .sum::<usize>()
+        };
+        let cells = CHUNK_SIZE / 2;
+        assert_eq!(count(&ChunkNeighbors::default()), 6 * cells * cells * 4);
+
+        // a single air block in a finer neighbor opens up the whole cell face next to it
+        let east = BlockFace::East as usize;
+        let mut neighbors = ChunkNeighbors::default();
+        neighbors.chunks[east] = Some(Arc::new(chunk.clone()));
+        let covered = count(&neighbors);
+        let mut open = chunk.clone();
+        open.set_block(0, 0, 0, AIR_BLOCK_ID);
+        neighbors.chunks[east] = Some(Arc::new(open));
+        assert_eq!(count(&neighbors), covered + 4);
+    }
+
+    #[test]
+    fn skirts_only_hang_towards_other_levels_of_detail() {
+        let models = block_models();
+        let chunk = chunk_of(|_, y, _| if y < CHUNK_SIZE / 2 { STONE } else { AIR_BLOCK_ID });
+        let count = |neighbor_lod| {
+            let mut neighbors = ChunkNeighbors::default();
+            neighbors.chunks[BlockFace::East as usize] = Some(Arc::new(chunk.clone()));
+            neighbors.lods[BlockFace::East as usize] = neighbor_lod;
+            let options =
+                MeshingOptions { mode: MeshingMode::Greedy, ambient_occlusion: true, lod: 0 };
+            build_chunk_vertices(&chunk, &neighbors, &models, options)
+                .iter()
+                .map(Vec::len)
+                .sum::<usize>()
+        };
+
+        // the neighbor covers the border either way, only the surface edge gets a skirt
+        assert_eq!(count(1), count(0) + CHUNK_SIZE * 4);
     }
 }
@@ -0,0 +1,273 @@
+use rapidhash::RapidHashMap;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+
+use crate::content::world::chunks::chunk::Chunk;
+use crate::content::world::chunks::chunk_mesh::{self, ChunkNeighbors, LayerVertices, MeshingOptions};
+use crate::content::world::generation::WorldGenerator;
+use crate::content::world::lighting::{self, BlockLight};
+use crate::engine::rendering::baked_model::BakedModel;
+
+/// Handle to a queued job. Setting `cancelled` makes workers skip the job if they haven't
+/// started it yet, and `id` tells results of replaced jobs apart from the current one.
+struct Ticket {
+    id: u64,
+    cancelled: Arc<AtomicBool>,
+}
+
+enum JobKind {
+    Generate {
+        position: [i32; 3],
+        generator: Arc<dyn WorldGenerator>,
+        light_properties: Arc<RapidHashMap<String, BlockLight>>,
+    },
+    Mesh {
+        chunk: Arc<Chunk>,
+        neighbors: ChunkNeighbors,
+        block_models: Arc<RapidHashMap<String, BakedModel>>,
+        options: MeshingOptions,
+    },
+}
+
+struct Job {
+    id: u64,
+    cancelled: Arc<AtomicBool>,
+    kind: JobKind,
+}
+
+/// Jobs waiting for a worker. Urgent jobs (like remeshing an edited chunk) go to the front.
+struct JobQueue {
+    jobs: Mutex<VecDeque<Job>>,
+    available: Condvar,
+    shutdown: AtomicBool,
+}
+
+struct GeneratedChunk {
+    id: u64,
+    chunk: Chunk,
+}
+
+/// Vertices of a chunk mesh that still have to be uploaded on the main thread
+pub struct MeshedChunk {
+    id: u64,
+    pub position: [i32; 3],
+    pub vertices: LayerVertices,
+}
+
+/// Runs chunk generation and mesh building on background threads.
+/// Results come back through channels and are only handed out if their job is still current,
+/// so cancelled or replaced jobs never reach the world.
+pub struct ChunkWorkerPool {
+    queue: Arc<JobQueue>,
+    workers: Vec<JoinHandle<()>>,
+    generated_receiver: Receiver<GeneratedChunk>,
+    meshed_receiver: Receiver<MeshedChunk>,
+    pending_generation: HashMap<[i32; 3], Ticket>,
+    pending_meshes: HashMap<[i32; 3], Ticket>,
+    next_job_id: u64,
+}
+
+impl ChunkWorkerPool {
+    /// Spawns one worker per core, leaving one for the render thread
+    pub fn new() -> Self {
+        let worker_count = thread::available_parallelism()
+            .map(|n| n.get().saturating_sub(1))
+            .unwrap_or(1)
+            .max(1);
+
+        let queue = Arc::new(JobQueue {
+            jobs: Mutex::new(VecDeque::new()),
+            available: Condvar::new(),
+            shutdown: AtomicBool::new(false),
+        });
+        let (generated_sender, generated_receiver) = mpsc::channel();
+        let (meshed_sender, meshed_receiver) = mpsc::channel();
+
+        let workers = (0..worker_count)
+            .map(|i| {
+                let queue = queue.clone();
+                let generated_sender = generated_sender.clone();
+                let meshed_sender = meshed_sender.clone();
+                thread::Builder::new()
+                    .name(format!("chunk worker {i}"))
+                    .spawn(move || Self::run_worker(&queue, &generated_sender, &meshed_sender))
+                    .expect("failed to spawn chunk worker")
+            })
+            .collect();
+
+        Self {
+            queue,
+            workers,
+            generated_receiver,
+            meshed_receiver,
+            pending_generation: HashMap::new(),
+            pending_meshes: HashMap::new(),
+            next_job_id: 0,
+        }
+    }
+
+    pub fn is_generating(&self, position: [i32; 3]) -> bool {
+        self.pending_generation.contains_key(&position)
+    }
+
+    /// Queues generation of a new chunk, unless it's already being generated. The worker also
+    /// lights the chunk on its own, so only its borders are left for the main thread.
+    pub fn generate(
+        &mut self,
+        position: [i32; 3],
+        generator: Arc<dyn WorldGenerator>,
+        light_properties: Arc<RapidHashMap<String, BlockLight>>,
+    ) {
+        if self.is_generating(position) {
+            return;
+        }
+
+        let (id, cancelled) = self.new_ticket();
+        self.pending_generation.insert(position, Ticket { id, cancelled: cancelled.clone() });
+        let kind = JobKind::Generate { position, generator, light_properties };
+        self.push(Job { id, cancelled, kind }, false);
+    }
+
+    /// Queues building the vertices of a chunk, replacing any mesh job still pending for it.
+    /// Urgent jobs skip ahead of everything already queued.
+    pub fn mesh(
+        &mut self,
+        chunk: Arc<Chunk>,
+        neighbors: ChunkNeighbors,
+        block_models: Arc<RapidHashMap<String, BakedModel>>,
+        options: MeshingOptions,
+        urgent: bool,
+    ) {
+        let (id, cancelled) = self.new_ticket();
+        let ticket = Ticket { id, cancelled: cancelled.clone() };
+        if let Some(old) = self.pending_meshes.insert(chunk.position, ticket) {
+            old.cancelled.store(true, Ordering::Relaxed);
+        }
+
+        let kind = JobKind::Mesh { chunk, neighbors, block_models, options };
+        self.push(Job { id, cancelled, kind }, urgent);
+    }
+
+    /// Cancels every pending job for chunk positions `keep` returns false for
+    pub fn retain(&mut self, mut keep: impl FnMut(&[i32; 3]) -> bool) {
+        for pending in [&mut self.pending_generation, &mut self.pending_meshes] {
+            pending.retain(|position, ticket| {
+                let kept = keep(position);
+                if !kept {
+                    ticket.cancelled.store(true, Ordering::Relaxed);
+                }
+                kept
+            });
+        }
+    }
+
+    /// Takes all chunks that finished generating since the last call
+    pub fn poll_generated(&mut self) -> Vec<Chunk> {
+        let mut chunks = Vec::new();
+        while let Ok(result) = self.generated_receiver.try_recv() {
+            let position = result.chunk.position;
+            if self.pending_generation.get(&position).is_some_and(|t| t.id == result.id) {
+                self.pending_generation.remove(&position);
+                chunks.push(result.chunk);
+            }
+        }
+        chunks
+    }
+
+    /// Takes up to `budget` finished meshes, the rest stay queued for the next call
+    pub fn poll_meshed(&mut self, budget: usize) -> Vec<MeshedChunk> {
+        let mut meshes = Vec::new();
+        while meshes.len() < budget {
+            let Ok(result) = self.meshed_receiver.try_recv() else {
+                break;
+            };
+            if self.pending_meshes.get(&result.position).is_some_and(|t| t.id == result.id) {
+                self.pending_meshes.remove(&result.position);
+                meshes.push(result);
+            }
+        }
+        meshes
+    }
+
+    fn new_ticket(&mut self) -> (u64, Arc<AtomicBool>) {
+        self.next_job_id += 1;
+        (self.next_job_id, Arc::new(AtomicBool::new(false)))
+    }
+
+    fn push(&self, job: Job, urgent: bool) {
+        let mut jobs = self.queue.jobs.lock().unwrap();
+        if urgent {
+            jobs.push_front(job);
+        } else {
+            jobs.push_back(job);
+        }
+        self.queue.available.notify_one();
+    }
+
+    fn run_worker(
+        queue: &JobQueue,
+        generated_sender: &Sender<GeneratedChunk>,
+        meshed_sender: &Sender<MeshedChunk>,
+    ) {
+        loop {
+            let job = {
+                let mut jobs = queue.jobs.lock().unwrap();
+                loop {
+                    if queue.shutdown.load(Ordering::Relaxed) {
+                        return;
+                    }
+                    if let Some(job) = jobs.pop_front() {
+                        break job;
+                    }
+                    jobs = queue.available.wait(jobs).unwrap();
+                }
+            };
+
+            if job.cancelled.load(Ordering::Relaxed) {
+                continue;
+            }
+
+            // a failed send means the pool is gone, so the worker can stop
+            let sent = match job.kind {
+                JobKind::Generate { position, generator, light_properties } => {
+                    let mut chunk = Chunk::new(position);
+                    generator.generate(&mut chunk);
+                    lighting::light_chunk(&mut chunk, &light_properties);
+                    generated_sender.send(GeneratedChunk { id: job.id, chunk }).is_ok()
+                }
+                JobKind::Mesh { chunk, neighbors, block_models, options } => {
+                    let vertices =
+                        chunk_mesh::build_chunk_vertices(&chunk, &neighbors, &block_models, options);
+                    let meshed = MeshedChunk { id: job.id, position: chunk.position, vertices };
+                    meshed_sender.send(meshed).is_ok()
+                }
+            };
+            if !sent {
+                return;
+            }
+        }
+    }
+}
+
+impl Default for ChunkWorkerPool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for ChunkWorkerPool {
+    fn drop(&mut self) {
+        // set while holding the lock so no worker can miss the wakeup between checking and waiting
+        {
+            let _jobs = self.queue.jobs.lock().unwrap();
+            self.queue.shutdown.store(true, Ordering::Relaxed);
+        }
+        self.queue.available.notify_all();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use crate::content::block::block_state;
+use crate::content::world::chunks::chunk::AIR_BLOCK_ID;
+use crate::content::world::world::World;
+use crate::engine::core::content_loader::GameContent;
+use crate::utils::math::Aabb;
+use glam::Vec3;
+
+/// How far a box may reach into a block before it counts as overlapping it, so boxes resting
+/// exactly on a face don't get caught on it through float error
+const COLLISION_EPSILON: f32 = 1e-4;
+
+/// Downwards acceleration of anything that falls, in blocks per second squared
+pub const GRAVITY: f32 = 25.0;
+pub const TERMINAL_VELOCITY: f32 = 60.0;
+
+impl World {
+    /// Whether the block at world space coordinates stops movement. Chunks that aren't in memory
+    /// count as solid, so nothing falls into terrain that hasn't been generated yet.
+    pub fn is_solid_world(&self, wx: i32, wy: i32, wz: i32, content: &GameContent) -> bool {
+        let Some(state) = self.get_block_state_world(wx, wy, wz) else {
+            return true;
+        };
+        state != AIR_BLOCK_ID
+            && content
+                .blocks
+                .get(block_state::block_id(state))
+                .is_some_and(|block| block.properties.solid)
+    }
+
+    /// Moves `aabb` by `motion` one axis at a time, y first and then x and z. Each axis stops at
+    /// the face of the first solid block in the way, so the box slides along walls and floors.
+    /// Blocks the box already overlaps don't stop it, so it can always move out of them.
+    /// Returns the moved box and which axes got cut short.
+    pub fn sweep_aabb(
+        &self,
+        mut aabb: Aabb,
+        motion: Vec3,
+        content: &GameContent,
+    ) -> (Aabb, [bool; 3]) {
+        let mut blocked = [false; 3];
+        for axis in [1, 0, 2] {
+            let moved = self.sweep_axis(&aabb, axis, motion[axis], content);
+            blocked[axis] = moved != motion[axis];
+            aabb.min[axis] += moved;
+            aabb.max[axis] += moved;
+        }
+        (aabb, blocked)
+    }
+
+    /// How far `aabb` gets along `axis` before a solid block stops it, up to `distance`.
+    /// Walks the layers of blocks in front of the box from nearest to furthest.
+    fn sweep_axis(&self, aabb: &Aabb, axis: usize, distance: f32, content: &GameContent) -> f32 {
+        if distance == 0.0 {
+            return 0.0;
+        }
+
+        // blocks the box covers on the two other axes
+        let cover = |axis: usize| {
+            (aabb.min[axis] + COLLISION_EPSILON).floor() as i32
+                ..(aabb.max[axis] - COLLISION_EPSILON).ceil() as i32
+        };
+        let (u_axis, v_axis) = ((axis + 1) % 3, (axis + 2) % 3);
+        let layer_is_solid = |layer: i32| {
+            cover(u_axis).any(|u| {
+                cover(v_axis).any(|v| {
+                    let mut pos = [0; 3];
+                    pos[axis] = layer;
+                    pos[u_axis] = u;
+                    pos[v_axis] = v;
+                    self.is_solid_world(pos[0], pos[1], pos[2], content)
+                })
+            })
+        };
+
+        if distance > 0.0 {
+            let first = (aabb.max[axis] - COLLISION_EPSILON).ceil() as i32;
+            let last = (aabb.max[axis] + distance).ceil() as i32 - 1;
+            match (first..=last).find(|&layer| layer_is_solid(layer)) {
+                Some(layer) => layer as f32 - aabb.max[axis],
+                None => distance,
+            }
+        } else {
+            let first = (aabb.min[axis] + COLLISION_EPSILON).floor() as i32 - 1;
+            let last = (aabb.min[axis] + distance).floor() as i32;
+            match (last..=first).rev().find(|&layer| layer_is_solid(layer)) {
+                Some(layer) => layer as f32 + 1.0 - aabb.min[axis],
+                None => distance,
+            }
+        }
+    }
+}
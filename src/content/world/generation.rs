@@ -0,0 +1,205 @@
+use crate::content::world::chunks::chunk::{AIR_BLOCK_ID, CHUNK_SIZE, Chunk};
+use noise::{Fbm, MultiFractal, NoiseFn, Perlin};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const GRASS_BLOCK_ID: &str = "template:grass";
+pub const DIRT_BLOCK_ID: &str = "template:dirt";
+pub const STONE_BLOCK_ID: &str = "template:stone";
+
+/// Fills freshly created chunks with terrain.
+/// Generation has to be deterministic: the same generator settings and chunk position must always
+/// produce the same chunk, since unedited chunks are never saved and get regenerated on load.
+pub trait WorldGenerator: Send + Sync {
+    /// Fills the given chunk based on its `position`
+    fn generate(&self, chunk: &mut Chunk);
+}
+
+/// The original test terrain: a flat dirt surface halfway through the chunks at y = 0
+pub struct FlatGenerator;
+
+impl WorldGenerator for FlatGenerator {
+    fn generate(&self, chunk: &mut Chunk) {
+        if chunk.position[1] > 0 {
+            chunk.generate_empty();
+        } else if chunk.position[1] == 0 {
+            chunk.generate_flat(DIRT_BLOCK_ID);
+        } else {
+            chunk.generate_full(DIRT_BLOCK_ID);
+        }
+    }
+}
+
+/// Rolling hills from a seeded 2D fBm Perlin heightmap.
+/// Each column is grass on top, a few blocks of dirt below that, and stone all the way down.
+pub struct NoiseGenerator {
+    heightmap: Fbm<Perlin>,
+}
+
+impl NoiseGenerator {
+    /// World y of the surface where the heightmap is 0
+    const BASE_HEIGHT: f64 = 4.0;
+    /// How far the surface goes above and below `BASE_HEIGHT`
+    const HEIGHT_AMPLITUDE: f64 = 24.0;
+    /// Heightmap features per block, lower is smoother
+    const FREQUENCY: f64 = 0.005;
+    const OCTAVES: usize = 5;
+    /// Depth of the dirt layer below the grass
+    const SUBSURFACE_DEPTH: i32 = 3;
+
+    pub fn new(seed: u32) -> Self {
+        Self {
+            heightmap: Fbm::<Perlin>::new(seed)
+                .set_octaves(Self::OCTAVES)
+                .set_frequency(Self::FREQUENCY),
+        }
+    }
+
+    /// World y of the topmost solid block of the column at the given world x/z
+    pub fn surface_height(&self, wx: i32, wz: i32) -> i32 {
+        let noise = self.heightmap.get([wx as f64, wz as f64]);
+        (Self::BASE_HEIGHT + noise * Self::HEIGHT_AMPLITUDE).floor() as i32
+    }
+}
+
+impl WorldGenerator for NoiseGenerator {
+    fn generate(&self, chunk: &mut Chunk) {
+        let origin = chunk.position.map(|c| c * CHUNK_SIZE as i32);
+
+        let mut heights = [0; CHUNK_SIZE * CHUNK_SIZE];
+        for z in 0..CHUNK_SIZE {
+            for x in 0..CHUNK_SIZE {
+                heights[x + z * CHUNK_SIZE] =
+                    self.surface_height(origin[0] + x as i32, origin[2] + z as i32);
+            }
+        }
+
+        // the whole chunk is above the terrain, Chunk::new is already all air
+        if heights.iter().all(|&h| h < origin[1]) {
+            return;
+        }
+
+        for z in 0..CHUNK_SIZE {
+            for x in 0..CHUNK_SIZE {
+                let surface = heights[x + z * CHUNK_SIZE];
+                for y in 0..CHUNK_SIZE {
+                    let wy = origin[1] + y as i32;
+                    let block_id = if wy > surface {
+                        AIR_BLOCK_ID
+                    } else if wy == surface {
+                        GRASS_BLOCK_ID
+                    } else if wy >= surface - Self::SUBSURFACE_DEPTH {
+                        DIRT_BLOCK_ID
+                    } else {
+                        STONE_BLOCK_ID
+                    };
+                    chunk.set_block(x, y, z, block_id);
+                }
+            }
+        }
+    }
+}
+
+/// Which `WorldGenerator` a world uses
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum GeneratorKind {
+    Flat,
+    #[default]
+    Noise,
+}
+
+/// Per-world settings, stored as `world.json5` in the world's directory
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WorldSettings {
+    #[serde(default)]
+    pub generator: GeneratorKind,
+    #[serde(default)]
+    pub seed: u32,
+}
+
+impl WorldSettings {
+    const FILE_NAME: &'static str = "world.json5";
+
+    /// Loads the world's settings, or creates them with a random seed for a new world.
+    /// New settings are written back right away so the seed stays the same across sessions.
+    pub fn load_or_create(world_dir: &Path) -> Self {
+        let path = world_dir.join(Self::FILE_NAME);
+
+        if let Ok(content) = fs::read_to_string(&path) {
+            match serde_json5::from_str(&content) {
+                Ok(settings) => return settings,
+                Err(e) => panic!("Failed to parse {path:?}: {e}"), // don't overwrite a broken world
+            }
+        }
+
+        let settings = Self {
+            generator: GeneratorKind::default(),
+            seed: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_nanos() as u32)
+                .unwrap_or_default(),
+        };
+
+        let written = fs::create_dir_all(world_dir).and_then(|_| {
+            let content = serde_json::to_string_pretty(&settings).map_err(std::io::Error::other)?;
+            fs::write(&path, content)
+        });
+        if let Err(e) = written {
+            eprintln!("Failed to write {path:?}: {e}");
+        }
+
+        settings
+    }
+
+    pub fn create_generator(&self) -> Arc<dyn WorldGenerator> {
+        match self.generator {
+            GeneratorKind::Flat => Arc::new(FlatGenerator),
+            GeneratorKind::Noise => Arc::new(NoiseGenerator::new(self.seed)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POSITIONS: [[i32; 3]; 4] = [[0, 0, 0], [3, -1, -2], [-7, 0, 12], [40, 1, -40]];
+
+    fn generate(generator: &dyn WorldGenerator, position: [i32; 3]) -> Chunk {
+        let mut chunk = Chunk::new(position);
+        generator.generate(&mut chunk);
+        chunk
+    }
+
+    #[test]
+    fn noise_generation_is_deterministic() {
+        let generator = NoiseGenerator::new(1234);
+        let fresh = NoiseGenerator::new(1234);
+
+        let mut has_terrain = false;
+        for position in POSITIONS {
+            let first = generate(&generator, position);
+            for again in [generate(&generator, position), generate(&fresh, position)] {
+                assert_eq!(first.palette, again.palette, "chunk {position:?}");
+                assert_eq!(first.blocks, again.blocks, "chunk {position:?}");
+            }
+            has_terrain |= first.palette.len() > 1;
+        }
+        assert!(has_terrain, "no test chunk has any terrain in it");
+    }
+
+    #[test]
+    fn seeds_change_the_terrain() {
+        let a = NoiseGenerator::new(1);
+        let b = NoiseGenerator::new(2);
+        let differs = (0..64).any(|i| {
+            let (wx, wz) = (i * 16, i * 7);
+            a.surface_height(wx, wz) != b.surface_height(wx, wz)
+        });
+        assert!(differs);
+    }
+}
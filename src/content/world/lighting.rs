@@ -0,0 +1,724 @@
+use crate::content::block::block::{Block, BlockProperties};
+use crate::content::block::block_state;
+use crate::content::world::chunks::chunk::{AIR_BLOCK_ID, CHUNK_SIZE, Chunk};
+use crate::content::world::chunks::chunk_mesh::ChunkNeighbors;
+use rapidhash::RapidHashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+
+pub const MAX_LIGHT: u8 = BlockProperties::MAX_LIGHT_LEVEL;
+
+const DOWN: [i32; 3] = [0, -1, 0];
+
+/// Light levels (0 to 15) of every block in a chunk, packed two per byte
+#[derive(Clone)]
+pub struct LightArray {
+    nibbles: Vec<u8>,
+}
+
+impl LightArray {
+    pub fn new() -> Self {
+        Self {
+            nibbles: vec![0; CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE / 2],
+        }
+    }
+
+    #[inline(always)]
+    pub fn get(&self, idx: usize) -> u8 {
+        let byte = self.nibbles[idx / 2];
+        if idx.is_multiple_of(2) { byte & 0x0F } else { byte >> 4 }
+    }
+
+    #[inline(always)]
+    pub fn set(&mut self, idx: usize, level: u8) {
+        let byte = &mut self.nibbles[idx / 2];
+        if idx.is_multiple_of(2) {
+            *byte = (*byte & 0xF0) | (level & 0x0F);
+        } else {
+            *byte = (*byte & 0x0F) | (level << 4);
+        }
+    }
+}
+
+impl Default for LightArray {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LightChannel {
+    /// Light from the open sky, which goes straight down without getting weaker
+    Sky,
+    /// Light given off by blocks
+    Block,
+}
+
+/// The block properties lighting needs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockLight {
+    pub opaque: bool,
+    pub emission: u8,
+}
+
+impl BlockLight {
+    pub const AIR: BlockLight = BlockLight {
+        opaque: false,
+        emission: 0,
+    };
+
+    /// Used for blocks missing from the table, which are drawn as solid cubes too
+    pub const UNKNOWN: BlockLight = BlockLight {
+        opaque: true,
+        emission: 0,
+    };
+
+    /// Lighting properties of every block, keyed by block ID
+    pub fn table<'a>(blocks: impl IntoIterator<Item = &'a Block>) -> RapidHashMap<String, BlockLight> {
+        blocks
+            .into_iter()
+            .map(|block| {
+                let light = BlockLight {
+                    opaque: block.properties.opaque,
+                    emission: block.properties.light_emission,
+                };
+                (block.id.clone(), light)
+            })
+            .collect()
+    }
+
+    fn of_state(table: &RapidHashMap<String, BlockLight>, state_key: &str) -> BlockLight {
+        if state_key == AIR_BLOCK_ID {
+            return BlockLight::AIR;
+        }
+        table
+            .get(block_state::block_id(state_key))
+            .copied()
+            .unwrap_or(BlockLight::UNKNOWN)
+    }
+}
+
+/// Flood fills light over the chunks of a world. Positions are world block coordinates, light
+/// crosses chunk borders freely but never goes into chunks that aren't in memory.
+///
+/// Light spreads to the six neighbors of a block, one level weaker per step, and stops at opaque
+/// blocks. Sky light enters through the top of chunks that have no chunk above them.
+pub(crate) struct Lighting<'a> {
+    chunks: &'a mut HashMap<[i32; 3], Arc<Chunk>>,
+    loaded_chunks: &'a mut HashMap<[i32; 3], Arc<Chunk>>,
+    properties: &'a RapidHashMap<String, BlockLight>,
+    /// Lighting of each palette entry of the chunks looked at so far
+    palette_lights: HashMap<[i32; 3], Vec<BlockLight>>,
+    /// Loaded chunks taken out of `loaded_chunks` while being written to, so writing doesn't copy
+    /// them. `finish` puts them back.
+    detached: HashSet<[i32; 3]>,
+    /// Chunks with changed light on or right next to them, which need new meshes
+    changed: HashSet<[i32; 3]>,
+}
+
+impl<'a> Lighting<'a> {
+    pub fn new(
+        chunks: &'a mut HashMap<[i32; 3], Arc<Chunk>>,
+        loaded_chunks: &'a mut HashMap<[i32; 3], Arc<Chunk>>,
+        properties: &'a RapidHashMap<String, BlockLight>,
+    ) -> Self {
+        Self {
+            chunks,
+            loaded_chunks,
+            properties,
+            palette_lights: HashMap::new(),
+            detached: HashSet::new(),
+            changed: HashSet::new(),
+        }
+    }
+
+    /// Puts written chunks back into the loaded chunks, and returns the chunks whose meshes
+    /// show changed light
+    pub fn finish(self) -> HashSet<[i32; 3]> {
+        for chunk_pos in self.detached {
+            if let Some(chunk) = self.chunks.get(&chunk_pos) {
+                self.loaded_chunks.insert(chunk_pos, chunk.clone());
+            }
+        }
+        self.changed
+    }
+
+    /// Connects the light of a chunk that was just added with the chunks around it. The chunk
+    /// has to be lit on its own with `light_chunk` already, so only its borders need work here:
+    /// a chunk above can shade it, it can shade the chunk below, and light crosses every border.
+    pub fn light_new_chunk(&mut self, chunk_pos: [i32; 3]) {
+        let origin = chunk_pos.map(|c| c * CHUNK_SIZE as i32);
+        let size = CHUNK_SIZE as i32;
+        let mut sky_queue = VecDeque::new();
+        let mut block_queue = VecDeque::new();
+
+        // `light_chunk` let the open sky in from above, which a chunk on top may block
+        let above = [chunk_pos[0], chunk_pos[1] + 1, chunk_pos[2]];
+        if self.chunks.contains_key(&above) {
+            let mut shaded = Vec::new();
+            for z in 0..size {
+                for x in 0..size {
+                    let over = [origin[0] + x, origin[1] + size, origin[2] + z];
+                    let top = [origin[0] + x, origin[1] + size - 1, origin[2] + z];
+                    if self.light(over, LightChannel::Sky) != Some(MAX_LIGHT)
+                        && self.light(top, LightChannel::Sky) == Some(MAX_LIGHT)
+                    {
+                        shaded.push((top, MAX_LIGHT));
+                    }
+                }
+            }
+            sky_queue.extend(self.remove(LightChannel::Sky, shaded));
+        }
+
+        // The chunk below lit its top from the open sky, which this chunk may cover now
+        let below = [chunk_pos[0], chunk_pos[1] - 1, chunk_pos[2]];
+        if self.chunks.contains_key(&below) {
+            let mut covered = Vec::new();
+            for z in 0..size {
+                for x in 0..size {
+                    let under = [origin[0] + x, origin[1] - 1, origin[2] + z];
+                    let bottom = [origin[0] + x, origin[1], origin[2] + z];
+                    if self.light(under, LightChannel::Sky) == Some(MAX_LIGHT)
+                        && self.light(bottom, LightChannel::Sky) != Some(MAX_LIGHT)
+                    {
+                        covered.push((under, MAX_LIGHT));
+                    }
+                }
+            }
+            sky_queue.extend(self.remove(LightChannel::Sky, covered));
+        }
+
+        // Light spreads both ways across each border, from the chunk's own border blocks into
+        // the neighbor and from the neighbor's border blocks into the chunk
+        for offset in ChunkNeighbors::OFFSETS {
+            let axis = offset.iter().position(|&c| c != 0).unwrap();
+            let (u_axis, v_axis) = ((axis + 1) % 3, (axis + 2) % 3);
+            let (inside, outside) = if offset[axis] > 0 { (size - 1, size) } else { (0, -1) };
+            for v in 0..size {
+                for u in 0..size {
+                    for layer in [inside, outside] {
+                        let mut pos = origin;
+                        pos[axis] += layer;
+                        pos[u_axis] += u;
+                        pos[v_axis] += v;
+
+                        if self.light(pos, LightChannel::Sky).is_some_and(|l| l > 0) {
+                            sky_queue.push_back(pos);
+                        }
+                        if self.light(pos, LightChannel::Block).is_some_and(|l| l > 0) {
+                            block_queue.push_back(pos);
+                        }
+                    }
+                }
+            }
+        }
+
+        self.propagate(LightChannel::Sky, sky_queue);
+        self.propagate(LightChannel::Block, block_queue);
+    }
+
+    /// Updates the light around a block that was just replaced
+    pub fn block_changed(&mut self, pos: [i32; 3]) {
+        let Some(block) = self.block_light(pos) else {
+            return;
+        };
+
+        for channel in [LightChannel::Sky, LightChannel::Block] {
+            // clear what used to reach or come from here, then fill it back in from what's left
+            let level = self.light(pos, channel).unwrap_or(0);
+            let mut queue = if level > 0 {
+                self.remove(channel, vec![(pos, level)])
+            } else {
+                VecDeque::new()
+            };
+
+            let source = self.source_level(pos, channel);
+            if source > 0 {
+                self.set_light(pos, channel, source);
+                queue.push_back(pos);
+            }
+
+            if !block.opaque {
+                for offset in ChunkNeighbors::OFFSETS {
+                    let next = offset_pos(pos, offset);
+                    if self.light(next, channel).is_some_and(|l| l > 0) {
+                        queue.push_back(next);
+                    }
+                }
+            }
+
+            self.propagate(channel, queue);
+        }
+    }
+
+    /// Spreads light outwards from the queued blocks
+    fn propagate(&mut self, channel: LightChannel, mut queue: VecDeque<[i32; 3]>) {
+        while let Some(pos) = queue.pop_front() {
+            let Some(level) = self.light(pos, channel) else {
+                continue;
+            };
+
+            for offset in ChunkNeighbors::OFFSETS {
+                let spread = if channel == LightChannel::Sky && offset == DOWN && level == MAX_LIGHT {
+                    MAX_LIGHT
+                } else {
+                    level.saturating_sub(1)
+                };
+                if spread == 0 {
+                    continue;
+                }
+
+                let next = offset_pos(pos, offset);
+                if self.block_light(next).is_none_or(|block| block.opaque) {
+                    continue;
+                }
+                if self.light(next, channel).is_some_and(|l| l < spread) {
+                    self.set_light(next, channel, spread);
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+
+    /// Darkens the given blocks (with the levels they had) and everything that was lit through
+    /// them. Returns the brighter blocks around the darkened area, which have to propagate again
+    /// to fill it back in.
+    fn remove(&mut self, channel: LightChannel, seeds: Vec<([i32; 3], u8)>) -> VecDeque<[i32; 3]> {
+        let mut relight = VecDeque::new();
+        for &(pos, _) in &seeds {
+            self.set_light(pos, channel, 0);
+        }
+
+        let mut queue = VecDeque::from(seeds);
+        while let Some((pos, level)) = queue.pop_front() {
+            for offset in ChunkNeighbors::OFFSETS {
+                let next = offset_pos(pos, offset);
+                let Some(next_level) = self.light(next, channel) else {
+                    continue;
+                };
+                if next_level == 0 {
+                    continue;
+                }
+
+                let lit_from_here = next_level < level
+                    || (channel == LightChannel::Sky
+                        && offset == DOWN
+                        && level == MAX_LIGHT
+                        && next_level == MAX_LIGHT);
+                if lit_from_here {
+                    self.set_light(next, channel, 0);
+                    queue.push_back((next, next_level));
+
+                    // light sources in the darkened area shine again
+                    let source = self.source_level(next, channel);
+                    if source > 0 {
+                        self.set_light(next, channel, source);
+                        relight.push_back(next);
+                    }
+                } else {
+                    relight.push_back(next);
+                }
+            }
+        }
+        relight
+    }
+
+    /// Light a block gives itself: its emission, or full sky light at the top of a chunk that
+    /// has nothing above it
+    fn source_level(&mut self, pos: [i32; 3], channel: LightChannel) -> u8 {
+        let Some(block) = self.block_light(pos) else {
+            return 0;
+        };
+        match channel {
+            LightChannel::Block => block.emission,
+            LightChannel::Sky => {
+                let (chunk_pos, _) = split(pos);
+                let top = pos[1].rem_euclid(CHUNK_SIZE as i32) == CHUNK_SIZE as i32 - 1;
+                let above = [chunk_pos[0], chunk_pos[1] + 1, chunk_pos[2]];
+                if !block.opaque && top && !self.chunks.contains_key(&above) {
+                    MAX_LIGHT
+                } else {
+                    0
+                }
+            }
+        }
+    }
+
+    /// Lighting properties of the block at `pos`, `None` if its chunk isn't in memory
+    fn block_light(&mut self, pos: [i32; 3]) -> Option<BlockLight> {
+        let (chunk_pos, idx) = split(pos);
+        let chunk = self.chunks.get(&chunk_pos)?;
+        let properties = self.properties;
+        let lights = self.palette_lights.entry(chunk_pos).or_insert_with(|| {
+            chunk
+                .palette
+                .iter()
+                .map(|key| BlockLight::of_state(properties, key))
+                .collect()
+        });
+        Some(lights[chunk.blocks[idx] as usize])
+    }
+
+    fn light(&self, pos: [i32; 3], channel: LightChannel) -> Option<u8> {
+        let (chunk_pos, idx) = split(pos);
+        Some(self.chunks.get(&chunk_pos)?.light(channel).get(idx))
+    }
+
+    fn set_light(&mut self, pos: [i32; 3], channel: LightChannel, level: u8) {
+        let (chunk_pos, idx) = split(pos);
+        if self.loaded_chunks.remove(&chunk_pos).is_some() {
+            self.detached.insert(chunk_pos);
+        }
+        let Some(chunk) = self.chunks.get_mut(&chunk_pos) else {
+            return;
+        };
+        Arc::make_mut(chunk).light_mut(channel).set(idx, level);
+
+        // faces of the neighboring chunk touching this block show its light too
+        self.changed.insert(chunk_pos);
+        for axis in 0..3 {
+            let local = pos[axis].rem_euclid(CHUNK_SIZE as i32);
+            let step = if local == 0 {
+                -1
+            } else if local == CHUNK_SIZE as i32 - 1 {
+                1
+            } else {
+                continue;
+            };
+            let mut neighbor_pos = chunk_pos;
+            neighbor_pos[axis] += step;
+            if self.chunks.contains_key(&neighbor_pos) {
+                self.changed.insert(neighbor_pos);
+            }
+        }
+    }
+}
+
+/// Lights a chunk on its own, as if nothing was around it: sky light falls in through its top
+/// and its light sources shine, but no light crosses its borders. This is the flood fill over the
+/// whole chunk, so it runs wherever the chunk was made (e.g. on a chunk worker), and
+/// `Lighting::light_new_chunk` only fixes up the borders once the chunk is in the world.
+pub fn light_chunk(chunk: &mut Chunk, properties: &RapidHashMap<String, BlockLight>) {
+    let lights: Vec<BlockLight> = chunk
+        .palette
+        .iter()
+        .map(|key| BlockLight::of_state(properties, key))
+        .collect();
+    let mut sky_queue = VecDeque::new();
+    let mut block_queue = VecDeque::new();
+
+    for z in 0..CHUNK_SIZE {
+        for x in 0..CHUNK_SIZE {
+            for y in (0..CHUNK_SIZE).rev() {
+                let idx = Chunk::index(x, y, z);
+                if lights[chunk.blocks[idx] as usize].opaque {
+                    break;
+                }
+                chunk.sky_light.set(idx, MAX_LIGHT);
+                sky_queue.push_back(idx);
+            }
+        }
+    }
+
+    for idx in 0..chunk.blocks.len() {
+        let emission = lights[chunk.blocks[idx] as usize].emission;
+        if emission > 0 {
+            chunk.block_light.set(idx, emission);
+            block_queue.push_back(idx);
+        }
+    }
+
+    propagate_within(chunk, &lights, LightChannel::Sky, sky_queue);
+    propagate_within(chunk, &lights, LightChannel::Block, block_queue);
+}
+
+/// `Lighting::propagate` for a single chunk, working on block indices instead of world positions
+fn propagate_within(
+    chunk: &mut Chunk,
+    lights: &[BlockLight],
+    channel: LightChannel,
+    mut queue: VecDeque<usize>,
+) {
+    while let Some(idx) = queue.pop_front() {
+        let level = chunk.light(channel).get(idx);
+        let local = [
+            idx % CHUNK_SIZE,
+            idx / CHUNK_SIZE % CHUNK_SIZE,
+            idx / (CHUNK_SIZE * CHUNK_SIZE),
+        ];
+
+        for offset in ChunkNeighbors::OFFSETS {
+            let spread = if channel == LightChannel::Sky && offset == DOWN && level == MAX_LIGHT {
+                MAX_LIGHT
+            } else {
+                level.saturating_sub(1)
+            };
+            if spread == 0 {
+                continue;
+            }
+
+            let next = [0, 1, 2].map(|axis| local[axis] as i32 + offset[axis]);
+            if next.iter().any(|&c| c < 0 || c >= CHUNK_SIZE as i32) {
+                continue;
+            }
+            let next_idx = Chunk::index(next[0] as usize, next[1] as usize, next[2] as usize);
+            if lights[chunk.blocks[next_idx] as usize].opaque {
+                continue;
+            }
+            if chunk.light(channel).get(next_idx) < spread {
+                chunk.light_mut(channel).set(next_idx, spread);
+                queue.push_back(next_idx);
+            }
+        }
+    }
+}
+
+/// Splits a world block position into its chunk position and index in that chunk
+#[inline(always)]
+fn split(pos: [i32; 3]) -> ([i32; 3], usize) {
+    let chunk_pos = pos.map(|c| c.div_euclid(CHUNK_SIZE as i32));
+    let local = pos.map(|c| c.rem_euclid(CHUNK_SIZE as i32) as usize);
+    (chunk_pos, Chunk::index(local[0], local[1], local[2]))
+}
+
+#[inline(always)]
+fn offset_pos(pos: [i32; 3], offset: [i32; 3]) -> [i32; 3] {
+    [pos[0] + offset[0], pos[1] + offset[1], pos[2] + offset[2]]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STONE: &str = "test:stone";
+    const TORCH: &str = "test:torch";
+    const TORCH_LIGHT: u8 = 14;
+
+    fn properties() -> RapidHashMap<String, BlockLight> {
+        let mut properties = RapidHashMap::default();
+        properties.insert(STONE.to_string(), BlockLight { opaque: true, emission: 0 });
+        properties.insert(TORCH.to_string(), BlockLight { opaque: false, emission: TORCH_LIGHT });
+        properties
+    }
+
+    /// Chunks lit the way the world lights them: on their own first, then joined at the borders
+    struct TestWorld {
+        chunks: HashMap<[i32; 3], Arc<Chunk>>,
+        loaded_chunks: HashMap<[i32; 3], Arc<Chunk>>,
+        properties: RapidHashMap<String, BlockLight>,
+    }
+
+    impl TestWorld {
+        fn new() -> Self {
+            Self {
+                chunks: HashMap::new(),
+                loaded_chunks: HashMap::new(),
+                properties: properties(),
+            }
+        }
+
+        fn add(&mut self, mut chunk: Chunk) {
+            let chunk_pos = chunk.position;
+            light_chunk(&mut chunk, &self.properties);
+            self.chunks.insert(chunk_pos, Arc::new(chunk));
+            let mut lighting =
+                Lighting::new(&mut self.chunks, &mut self.loaded_chunks, &self.properties);
+            lighting.light_new_chunk(chunk_pos);
+            lighting.finish();
+        }
+
+        fn set_block(&mut self, pos: [i32; 3], block: &str) {
+            let (chunk_pos, _) = split(pos);
+            let local = pos.map(|c| c.rem_euclid(CHUNK_SIZE as i32) as usize);
+            let chunk = self.chunks.get_mut(&chunk_pos).unwrap();
+            Arc::make_mut(chunk).set_block(local[0], local[1], local[2], block);
+
+            let mut lighting =
+                Lighting::new(&mut self.chunks, &mut self.loaded_chunks, &self.properties);
+            lighting.block_changed(pos);
+            lighting.finish();
+        }
+
+        fn light(&self, pos: [i32; 3], channel: LightChannel) -> u8 {
+            let (chunk_pos, idx) = split(pos);
+            self.chunks[&chunk_pos].light(channel).get(idx)
+        }
+    }
+
+    fn filled(chunk_pos: [i32; 3], block: &str) -> Chunk {
+        let mut chunk = Chunk::new(chunk_pos);
+        chunk.generate_full(block);
+        chunk
+    }
+
+    /// Stone with a tunnel along x at y = z = 8 that opens into the chunk at +x
+    fn tunnel_chunk() -> Chunk {
+        let mut chunk = filled([0, 0, 0], STONE);
+        for x in 0..CHUNK_SIZE {
+            chunk.set_block(x, 8, 8, AIR_BLOCK_ID);
+        }
+        chunk
+    }
+
+    /// Empty space under a stone roof in the chunk above, so the sky doesn't reach it
+    fn covered_world() -> TestWorld {
+        let mut world = TestWorld::new();
+        let mut roof = Chunk::new([0, 1, 0]);
+        for z in 0..CHUNK_SIZE {
+            for x in 0..CHUNK_SIZE {
+                roof.set_block(x, 0, z, STONE);
+            }
+        }
+        world.add(roof);
+        world.add(Chunk::new([0, 0, 0]));
+        world
+    }
+
+    #[test]
+    fn open_sky_lights_empty_chunk() {
+        let mut world = TestWorld::new();
+        world.add(Chunk::new([0, 0, 0]));
+
+        for pos in [[0, 0, 0], [8, 8, 8], [15, 15, 15]] {
+            assert_eq!(world.light(pos, LightChannel::Sky), MAX_LIGHT);
+            assert_eq!(world.light(pos, LightChannel::Block), 0);
+        }
+    }
+
+    #[test]
+    fn tunnel_is_lit_from_its_opening() {
+        let size = CHUNK_SIZE as i32;
+        let mut world = TestWorld::new();
+        world.add(tunnel_chunk());
+        world.add(Chunk::new([1, 0, 0]));
+
+        // full sky light at the opening, one level less for every block into the tunnel
+        assert_eq!(world.light([size, 8, 8], LightChannel::Sky), MAX_LIGHT);
+        for x in 0..size {
+            let expected = MAX_LIGHT.saturating_sub((size - x) as u8);
+            assert_eq!(world.light([x, 8, 8], LightChannel::Sky), expected, "x = {x}");
+        }
+        assert_eq!(world.light([4, 9, 8], LightChannel::Sky), 0);
+    }
+
+    #[test]
+    fn tunnel_light_does_not_depend_on_load_order() {
+        let mut tunnel_first = TestWorld::new();
+        tunnel_first.add(tunnel_chunk());
+        tunnel_first.add(Chunk::new([1, 0, 0]));
+
+        let mut opening_first = TestWorld::new();
+        opening_first.add(Chunk::new([1, 0, 0]));
+        opening_first.add(tunnel_chunk());
+
+        for x in 0..2 * CHUNK_SIZE as i32 {
+            assert_eq!(
+                tunnel_first.light([x, 8, 8], LightChannel::Sky),
+                opening_first.light([x, 8, 8], LightChannel::Sky),
+                "x = {x}"
+            );
+        }
+    }
+
+    #[test]
+    fn overhang_casts_a_fading_shadow() {
+        let mut chunk = Chunk::new([0, 0, 0]);
+        for z in 0..CHUNK_SIZE {
+            for x in 0..8 {
+                chunk.set_block(x, 10, z, STONE);
+            }
+        }
+        let mut world = TestWorld::new();
+        world.add(chunk);
+
+        assert_eq!(world.light([8, 5, 8], LightChannel::Sky), MAX_LIGHT);
+        assert_eq!(world.light([4, 11, 8], LightChannel::Sky), MAX_LIGHT);
+        // light comes in from under the edge of the overhang at x = 8
+        for x in 0..8 {
+            let expected = MAX_LIGHT - (8 - x) as u8;
+            assert_eq!(world.light([x, 5, 8], LightChannel::Sky), expected, "x = {x}");
+        }
+    }
+
+    #[test]
+    fn chunk_above_shades_chunk_below() {
+        let world = covered_world();
+        for pos in [[0, 0, 0], [8, 8, 8], [15, 15, 15]] {
+            assert_eq!(world.light(pos, LightChannel::Sky), 0);
+        }
+        assert_eq!(world.light([8, 17, 8], LightChannel::Sky), MAX_LIGHT);
+
+        // the same with the roof loaded after the chunk below it
+        let mut world = TestWorld::new();
+        world.add(Chunk::new([0, 0, 0]));
+        let mut roof = Chunk::new([0, 1, 0]);
+        for z in 0..CHUNK_SIZE {
+            for x in 0..CHUNK_SIZE {
+                roof.set_block(x, 0, z, STONE);
+            }
+        }
+        world.add(roof);
+        for pos in [[0, 0, 0], [8, 8, 8], [15, 15, 15]] {
+            assert_eq!(world.light(pos, LightChannel::Sky), 0);
+        }
+    }
+
+    #[test]
+    fn torch_lights_and_unlights() {
+        let mut world = covered_world();
+        world.set_block([8, 8, 8], TORCH);
+
+        assert_eq!(world.light([8, 8, 8], LightChannel::Block), TORCH_LIGHT);
+        assert_eq!(world.light([10, 8, 8], LightChannel::Block), TORCH_LIGHT - 2);
+        assert_eq!(world.light([8, 8, 0], LightChannel::Block), TORCH_LIGHT - 8);
+        assert_eq!(world.light([8, 8, 8], LightChannel::Sky), 0);
+
+        world.set_block([8, 8, 8], AIR_BLOCK_ID);
+        for z in 0..CHUNK_SIZE as i32 {
+            for y in 0..CHUNK_SIZE as i32 {
+                for x in 0..CHUNK_SIZE as i32 {
+                    assert_eq!(world.light([x, y, z], LightChannel::Block), 0);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn removing_a_torch_keeps_the_other_torch_light() {
+        let mut world = covered_world();
+        world.set_block([4, 8, 8], TORCH);
+        world.set_block([12, 8, 8], TORCH);
+        assert_eq!(world.light([8, 8, 8], LightChannel::Block), TORCH_LIGHT - 4);
+
+        world.set_block([4, 8, 8], AIR_BLOCK_ID);
+        assert_eq!(world.light([12, 8, 8], LightChannel::Block), TORCH_LIGHT);
+        assert_eq!(world.light([8, 8, 8], LightChannel::Block), TORCH_LIGHT - 4);
+        assert_eq!(world.light([4, 8, 8], LightChannel::Block), TORCH_LIGHT - 8);
+        assert_eq!(world.light([0, 8, 8], LightChannel::Block), TORCH_LIGHT - 12);
+    }
+
+    #[test]
+    fn torch_light_crosses_chunk_borders() {
+        let mut world = covered_world();
+        world.add(filled([1, 0, 0], AIR_BLOCK_ID));
+        world.set_block([15, 8, 8], TORCH);
+        assert_eq!(world.light([16, 8, 8], LightChannel::Block), TORCH_LIGHT - 1);
+        assert_eq!(world.light([20, 8, 8], LightChannel::Block), TORCH_LIGHT - 5);
+
+        world.set_block([15, 8, 8], AIR_BLOCK_ID);
+        assert_eq!(world.light([16, 8, 8], LightChannel::Block), 0);
+        assert_eq!(world.light([20, 8, 8], LightChannel::Block), 0);
+    }
+
+    #[test]
+    fn torch_in_a_new_chunk_lights_its_neighbor() {
+        let mut world = covered_world();
+        let mut chunk = Chunk::new([1, 0, 0]);
+        chunk.set_block(0, 8, 8, TORCH);
+        world.add(chunk);
+
+        assert_eq!(world.light([16, 8, 8], LightChannel::Block), TORCH_LIGHT);
+        assert_eq!(world.light([15, 8, 8], LightChannel::Block), TORCH_LIGHT - 1);
+        assert_eq!(world.light([10, 8, 8], LightChannel::Block), TORCH_LIGHT - 6);
+    }
+}
@@ -0,0 +1,207 @@
+use crate::content::block::block::Block;
+use crate::content::block::block_state;
+use crate::content::world::chunks::chunk::AIR_BLOCK_ID;
+use crate::content::world::world::World;
+use crate::engine::core::content_loader::GameContent;
+use glam::Vec3;
+use std::sync::Arc;
+
+/// Result of a successful `World::raycast`
+#[derive(Debug, Clone)]
+pub struct RaycastHit {
+    /// World space coordinates of the block that was hit
+    pub block_pos: [i32; 3],
+    /// Normal of the face the ray entered through, pointing out of the hit block.
+    /// Zero if the ray started inside the block.
+    pub normal: [i32; 3],
+    pub block: Arc<Block>,
+    /// Full state key of the block, including its properties
+    pub state: String,
+    /// Distance along the ray to the face that was hit
+    pub distance: f32,
+}
+
+impl RaycastHit {
+    /// Position of the block on the other side of the hit face, where a new block would go
+    pub fn adjacent_pos(&self) -> [i32; 3] {
+        [
+            self.block_pos[0] + self.normal[0],
+            self.block_pos[1] + self.normal[1],
+            self.block_pos[2] + self.normal[2],
+        ]
+    }
+}
+
+impl World {
+    /// Walks the voxel grid along the ray (Amanatides & Woo) and returns the first non-air block
+    /// within `max_distance`. Blocks occupy `[x, x + 1)` in world space, and chunks that
+    /// haven't been generated count as empty.
+    pub fn raycast(
+        &self,
+        origin: Vec3,
+        direction: Vec3,
+        max_distance: f32,
+        content: &GameContent,
+    ) -> Option<RaycastHit> {
+        let direction = direction.try_normalize()?;
+
+        let mut voxel = origin.floor().as_ivec3().to_array();
+        let mut normal = [0; 3];
+        let mut distance = 0.0;
+
+        let mut step = [0; 3];
+        let mut t_max = [f32::INFINITY; 3]; // ray distance until the next boundary on each axis
+        let mut t_delta = [f32::INFINITY; 3]; // ray distance to cross one whole block on each axis
+
+        for axis in 0..3 {
+            let dir = direction[axis];
+            if dir > 0.0 {
+                step[axis] = 1;
+                t_max[axis] = (voxel[axis] as f32 + 1.0 - origin[axis]) / dir;
+                t_delta[axis] = 1.0 / dir;
+            } else if dir < 0.0 {
+                step[axis] = -1;
+                t_max[axis] = (voxel[axis] as f32 - origin[axis]) / dir;
+                t_delta[axis] = -1.0 / dir;
+            }
+        }
+
+        loop {
+            if let Some(state) = self.get_block_state_world(voxel[0], voxel[1], voxel[2])
+                && state != AIR_BLOCK_ID
+                && let Some(block) = content.blocks.get(block_state::block_id(state))
+            {
+                return Some(RaycastHit {
+                    block_pos: voxel,
+                    normal,
+                    block,
+                    state: state.to_string(),
+                    distance,
+                });
+            }
+
+            // step into the neighbor across the closest boundary
+            let axis = if t_max[0] < t_max[1] {
+                if t_max[0] < t_max[2] { 0 } else { 2 }
+            } else if t_max[1] < t_max[2] {
+                1
+            } else {
+                2
+            };
+
+            distance = t_max[axis];
+            if distance > max_distance {
+                return None;
+            }
+
+            voxel[axis] += step[axis];
+            t_max[axis] += t_delta[axis];
+            normal = [0; 3];
+            normal[axis] = -step[axis];
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::content::world::test_support::{self, STONE};
+
+    fn world_with(blocks: &[[i32; 3]]) -> World {
+        let mut world = test_support::empty_world([-1, -1, -1], [0, 0, 0]);
+        for &[x, y, z] in blocks {
+            assert!(world.set_block_world(x, y, z, STONE));
+        }
+        world
+    }
+
+    #[test]
+    fn axis_aligned_rays_hit_the_facing_side() {
+        let content = test_support::content();
+        let world = world_with(&[[5, 2, 2]]);
+        let origin = Vec3::new(0.5, 2.5, 2.5);
+
+        let hit = world.raycast(origin, Vec3::X, 10.0, &content).unwrap();
+        assert_eq!(hit.block_pos, [5, 2, 2]);
+        assert_eq!(hit.normal, [-1, 0, 0]);
+        assert_eq!(hit.adjacent_pos(), [4, 2, 2]);
+        assert!((hit.distance - 4.5).abs() < 1e-5);
+        assert_eq!(hit.state, STONE);
+
+        // from above, straight down onto the top face
+        let hit = world.raycast(Vec3::new(5.5, 9.0, 2.5), Vec3::NEG_Y, 10.0, &content).unwrap();
+        assert_eq!(hit.block_pos, [5, 2, 2]);
+        assert_eq!(hit.normal, [0, 1, 0]);
+        assert!((hit.distance - 6.0).abs() < 1e-5);
+
+        assert!(world.raycast(origin, Vec3::NEG_X, 10.0, &content).is_none());
+    }
+
+    #[test]
+    fn negative_coordinates_are_floored() {
+        let content = test_support::content();
+        let world = world_with(&[[-3, -1, -6]]);
+
+        // -0.5 is inside block -1, not block 0
+        let origin = Vec3::new(-0.5, -0.5, -5.5);
+        let hit = world.raycast(origin, Vec3::NEG_X, 10.0, &content).unwrap();
+        assert_eq!(hit.block_pos, [-3, -1, -6]);
+        assert_eq!(hit.normal, [1, 0, 0]);
+        assert!((hit.distance - 1.5).abs() < 1e-5);
+
+        // diagonal ray crossing into the negative chunks
+        let origin = Vec3::new(0.5, 1.5, -4.5);
+        let direction = Vec3::new(-3.5, -2.5, -1.5);
+        let hit = world.raycast(origin, direction, 20.0, &content).unwrap();
+        assert_eq!(hit.block_pos, [-3, -1, -6]);
+    }
+
+    #[test]
+    fn starting_inside_a_block_hits_it_right_away() {
+        let content = test_support::content();
+        let world = world_with(&[[1, 1, 1]]);
+        let hit = world.raycast(Vec3::new(1.5, 1.2, 1.9), Vec3::Y, 5.0, &content).unwrap();
+        assert_eq!(hit.block_pos, [1, 1, 1]);
+        assert_eq!(hit.normal, [0, 0, 0]);
+        assert_eq!(hit.distance, 0.0);
+    }
+
+    #[test]
+    fn blocks_past_max_distance_are_missed() {
+        let content = test_support::content();
+        let world = world_with(&[[0, 0, 6]]);
+        let origin = Vec3::new(0.5, 0.5, 0.5);
+
+        // the face is 5.5 blocks away
+        assert!(world.raycast(origin, Vec3::Z, 5.4, &content).is_none());
+        let hit = world.raycast(origin, Vec3::Z, 5.6, &content).unwrap();
+        assert_eq!(hit.normal, [0, 0, -1]);
+    }
+
+    #[test]
+    fn reported_face_is_the_one_the_ray_enters() {
+        let content = test_support::content();
+        let world = world_with(&[[4, 4, 4]]);
+        let target = Vec3::new(4.5, 4.5, 4.5);
+
+        for (origin, normal) in [
+            (Vec3::new(4.5, 4.5, 1.0), [0, 0, -1]),
+            (Vec3::new(4.5, 4.5, 8.0), [0, 0, 1]),
+            (Vec3::new(1.0, 4.6, 4.4), [-1, 0, 0]),
+            (Vec3::new(8.0, 4.6, 4.4), [1, 0, 0]),
+            (Vec3::new(4.4, 1.0, 4.6), [0, -1, 0]),
+            (Vec3::new(4.4, 8.0, 4.6), [0, 1, 0]),
+        ] {
+            let hit = world.raycast(origin, target - origin, 10.0, &content).unwrap();
+            assert_eq!(hit.block_pos, [4, 4, 4]);
+            assert_eq!(hit.normal, normal, "from {origin}");
+        }
+    }
+
+    #[test]
+    fn zero_direction_hits_nothing() {
+        let content = test_support::content();
+        let world = world_with(&[[0, 0, 0]]);
+        assert!(world.raycast(Vec3::new(0.5, 0.5, 0.5), Vec3::ZERO, 5.0, &content).is_none());
+    }
+}
@@ -0,0 +1,380 @@
+use crate::content::world::chunks::chunk::{AIR_BLOCK_ID, CHUNK_SIZE, Chunk};
+use flate2::Compression;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Error, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+
+/// Chunks per axis stored in one region file
+pub const REGION_SIZE: i32 = 32;
+
+const REGION_CHUNKS: usize = (REGION_SIZE * REGION_SIZE * REGION_SIZE) as usize;
+
+const REGION_MAGIC: [u8; 4] = *b"DSPR";
+
+/// Bump whenever the layout of the header or the chunk encoding changes
+pub const REGION_VERSION: u32 = 1;
+
+/// Magic + version, followed by one (offset, length) pair per chunk slot
+const HEADER_SIZE: u64 = 8 + REGION_CHUNKS as u64 * 8;
+
+/// A single region file.
+///
+/// Layout: `"DSPR"`, `u32` version, then a table of `u32` byte offset and `u32` byte length for
+/// every chunk slot (zero length = not stored), then the zlib compressed chunk data.
+/// All integers are little endian. Rewritten chunks reuse their old spot if they still fit,
+/// otherwise they're appended to the end of the file.
+pub struct RegionFile {
+    file: File,
+    offsets: Vec<(u32, u32)>,
+}
+
+impl RegionFile {
+    /// Opens a region file, creating it with an empty offset table if it doesn't exist
+    pub fn open(path: &PathBuf) -> Result<Self, Error> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+
+        if file.metadata()?.len() == 0 {
+            let mut header = Vec::with_capacity(HEADER_SIZE as usize);
+            header.extend_from_slice(&REGION_MAGIC);
+            header.extend_from_slice(&REGION_VERSION.to_le_bytes());
+            header.resize(HEADER_SIZE as usize, 0);
+            file.write_all(&header)?;
+
+            return Ok(Self {
+                file,
+                offsets: vec![(0, 0); REGION_CHUNKS],
+            });
+        }
+
+        let mut header = vec![0; HEADER_SIZE as usize];
+        file.read_exact(&mut header)?;
+
+        if header[0..4] != REGION_MAGIC {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("{path:?} is not a region file"),
+            ));
+        }
+        let version = u32::from_le_bytes(header[4..8].try_into().unwrap());
+        if version != REGION_VERSION {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("{path:?} has region version {version}, expected {REGION_VERSION}"),
+            ));
+        }
+
+        let offsets = header[8..]
+            .chunks_exact(8)
+            .map(|entry| {
+                (
+                    u32::from_le_bytes(entry[0..4].try_into().unwrap()),
+                    u32::from_le_bytes(entry[4..8].try_into().unwrap()),
+                )
+            })
+            .collect();
+
+        Ok(Self { file, offsets })
+    }
+
+    /// Reads the chunk at the given chunk position, if it has been stored
+    pub fn read_chunk(&mut self, chunk_pos: [i32; 3]) -> Result<Option<Chunk>, Error> {
+        let (offset, length) = self.offsets[Self::slot(chunk_pos)];
+        if length == 0 {
+            return Ok(None);
+        }
+
+        let mut compressed = vec![0; length as usize];
+        self.file.seek(SeekFrom::Start(offset as u64))?;
+        self.file.read_exact(&mut compressed)?;
+
+        let mut bytes = Vec::new();
+        ZlibDecoder::new(compressed.as_slice()).read_to_end(&mut bytes)?;
+
+        decode_chunk(chunk_pos, &bytes).map(Some)
+    }
+
+    /// Writes the chunk into its slot and updates the offset table
+    pub fn write_chunk(&mut self, chunk: &Chunk) -> Result<(), Error> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&encode_chunk(chunk))?;
+        let compressed = encoder.finish()?;
+
+        let slot = Self::slot(chunk.position);
+        let (old_offset, old_length) = self.offsets[slot];
+
+        let offset = if old_length != 0 && compressed.len() <= old_length as usize {
+            old_offset as u64
+        } else {
+            self.file.seek(SeekFrom::End(0))?
+        };
+        let offset = u32::try_from(offset)
+            .map_err(|_| Error::other("region file is larger than 4 GiB"))?;
+
+        self.file.seek(SeekFrom::Start(offset as u64))?;
+        self.file.write_all(&compressed)?;
+
+        let entry = (offset, compressed.len() as u32);
+        let mut entry_bytes = [0; 8];
+        entry_bytes[0..4].copy_from_slice(&entry.0.to_le_bytes());
+        entry_bytes[4..8].copy_from_slice(&entry.1.to_le_bytes());
+        self.file.seek(SeekFrom::Start(8 + slot as u64 * 8))?;
+        self.file.write_all(&entry_bytes)?;
+
+        self.offsets[slot] = entry;
+        Ok(())
+    }
+
+    /// Index of a chunk inside its region's offset table
+    fn slot(chunk_pos: [i32; 3]) -> usize {
+        let local = chunk_pos.map(|c| c.rem_euclid(REGION_SIZE) as usize);
+        local[0] + local[1] * REGION_SIZE as usize + local[2] * (REGION_SIZE * REGION_SIZE) as usize
+    }
+}
+
+/// All region files of one world, opened lazily as chunks in them are needed
+pub struct RegionStorage {
+    dir: PathBuf,
+    regions: HashMap<[i32; 3], RegionFile>,
+}
+
+impl RegionStorage {
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        Self {
+            dir: dir.into(),
+            regions: HashMap::new(),
+        }
+    }
+
+    /// Loads a chunk from disk, `None` if it was never saved
+    pub fn load_chunk(&mut self, chunk_pos: [i32; 3]) -> Result<Option<Chunk>, Error> {
+        let region_pos = Self::region_pos(chunk_pos);
+        if !self.regions.contains_key(&region_pos) && !self.region_path(region_pos).exists() {
+            return Ok(None);
+        }
+
+        self.region(region_pos)?.read_chunk(chunk_pos)
+    }
+
+    pub fn save_chunk(&mut self, chunk: &Chunk) -> Result<(), Error> {
+        self.region(Self::region_pos(chunk.position))?
+            .write_chunk(chunk)
+    }
+
+    fn region(&mut self, region_pos: [i32; 3]) -> Result<&mut RegionFile, Error> {
+        if !self.regions.contains_key(&region_pos) {
+            fs::create_dir_all(&self.dir)?;
+            let region = RegionFile::open(&self.region_path(region_pos))?;
+            self.regions.insert(region_pos, region);
+        }
+
+        Ok(self.regions.get_mut(&region_pos).unwrap())
+    }
+
+    fn region_path(&self, region_pos: [i32; 3]) -> PathBuf {
+        self.dir.join(format!(
+            "r.{}.{}.{}.region",
+            region_pos[0], region_pos[1], region_pos[2]
+        ))
+    }
+
+    fn region_pos(chunk_pos: [i32; 3]) -> [i32; 3] {
+        chunk_pos.map(|c| c.div_euclid(REGION_SIZE))
+    }
+}
+
+/// Serializes a chunk as its palette followed by one palette index per block.
+///
+/// Layout: `u16` palette length, then per entry a `u16` byte length and the UTF-8 block ID,
+/// then `CHUNK_SIZE^3` `u16` palette indices in `Chunk::index` order. Little endian.
+pub fn encode_chunk(chunk: &Chunk) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(2 + chunk.blocks.len() * 2);
+
+    bytes.extend_from_slice(&(chunk.palette.len() as u16).to_le_bytes());
+    for block_id in &chunk.palette {
+        bytes.extend_from_slice(&(block_id.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(block_id.as_bytes());
+    }
+
+    for palette_idx in &chunk.blocks {
+        bytes.extend_from_slice(&palette_idx.to_le_bytes());
+    }
+
+    bytes
+}
+
+/// Reverses `encode_chunk`
+pub fn decode_chunk(position: [i32; 3], bytes: &[u8]) -> Result<Chunk, Error> {
+    let invalid = |msg: &str| Error::new(ErrorKind::InvalidData, format!("chunk {position:?}: {msg}"));
+
+    let mut cursor = bytes;
+    let read_u16 = |cursor: &mut &[u8]| -> Result<u16, Error> {
+        let (value, rest) = cursor
+            .split_first_chunk::<2>()
+            .ok_or_else(|| invalid("unexpected end of data"))?;
+        *cursor = rest;
+        Ok(u16::from_le_bytes(*value))
+    };
+
+    let palette_len = read_u16(&mut cursor)? as usize;
+    let mut palette = Vec::with_capacity(palette_len);
+    for _ in 0..palette_len {
+        let id_len = read_u16(&mut cursor)? as usize;
+        if cursor.len() < id_len {
+            return Err(invalid("unexpected end of data"));
+        }
+        let (id, rest) = cursor.split_at(id_len);
+        cursor = rest;
+        palette.push(String::from_utf8(id.to_vec()).map_err(|_| invalid("block ID isn't UTF-8"))?);
+    }
+
+    if palette.first().map(String::as_str) != Some(AIR_BLOCK_ID) {
+        return Err(invalid("palette doesn't start with air"));
+    }
+
+    let mut blocks = Vec::with_capacity(CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE);
+    for _ in 0..CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE {
+        let palette_idx = read_u16(&mut cursor)?;
+        if palette_idx as usize >= palette.len() {
+            return Err(invalid("palette index out of range"));
+        }
+        blocks.push(palette_idx);
+    }
+
+    Ok(Chunk::from_palette(position, palette, blocks))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_same_blocks(a: &Chunk, b: &Chunk) {
+        assert_eq!(a.position, b.position);
+        assert_eq!(a.palette, b.palette);
+        assert_eq!(a.blocks, b.blocks);
+    }
+
+    /// Several block types scattered without a pattern, so the chunk compresses badly
+    fn mixed_chunk(position: [i32; 3]) -> Chunk {
+        let ids = ["test:stone", "test:dirt", "test:log[axis=x]", "test:log[axis=z]", AIR_BLOCK_ID];
+        let mut chunk = Chunk::new(position);
+        let mut state = 0x2545_f491_u32;
+        for z in 0..CHUNK_SIZE {
+            for y in 0..CHUNK_SIZE {
+                for x in 0..CHUNK_SIZE {
+                    state ^= state << 13;
+                    state ^= state >> 17;
+                    state ^= state << 5;
+                    chunk.set_block(x, y, z, ids[state as usize % ids.len()]);
+                }
+            }
+        }
+        chunk
+    }
+
+    /// An empty directory for one test's region files
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("despawn_region_{}_{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn empty_chunk_round_trips() {
+        let chunk = Chunk::new([1, -2, 3]);
+        let decoded = decode_chunk(chunk.position, &encode_chunk(&chunk)).unwrap();
+        assert_same_blocks(&chunk, &decoded);
+        assert_eq!(decoded.palette, vec![AIR_BLOCK_ID.to_string()]);
+    }
+
+    #[test]
+    fn full_chunk_round_trips() {
+        let mut chunk = Chunk::new([0, 0, 0]);
+        chunk.generate_full("test:stone");
+        let decoded = decode_chunk(chunk.position, &encode_chunk(&chunk)).unwrap();
+        assert_same_blocks(&chunk, &decoded);
+        assert!(decoded.blocks.iter().all(|&idx| idx == 1));
+    }
+
+    #[test]
+    fn mixed_palette_chunk_round_trips() {
+        let chunk = mixed_chunk([-5, 7, -9]);
+        let decoded = decode_chunk(chunk.position, &encode_chunk(&chunk)).unwrap();
+        assert_same_blocks(&chunk, &decoded);
+        assert_eq!(decoded.palette_map["test:log[axis=z]"], chunk.palette_map["test:log[axis=z]"]);
+    }
+
+    #[test]
+    fn truncated_data_is_rejected() {
+        let bytes = encode_chunk(&mixed_chunk([0, 0, 0]));
+        let result = decode_chunk([0, 0, 0], &bytes[..bytes.len() - 1]);
+        assert!(result.is_err_and(|error| error.kind() == ErrorKind::InvalidData));
+    }
+
+    #[test]
+    fn region_file_round_trips_chunks() {
+        let dir = test_dir("round_trip");
+        let path = dir.join("r.0.0.0.region");
+        let empty = Chunk::new([0, 0, 0]);
+        let mixed = mixed_chunk([-1, 3, 31]);
+        {
+            let mut region = RegionFile::open(&path).unwrap();
+            assert!(region.read_chunk([4, 4, 4]).unwrap().is_none());
+            region.write_chunk(&empty).unwrap();
+            region.write_chunk(&mixed).unwrap();
+        }
+
+        let mut region = RegionFile::open(&path).unwrap();
+        assert_same_blocks(&empty, &region.read_chunk(empty.position).unwrap().unwrap());
+        assert_same_blocks(&mixed, &region.read_chunk(mixed.position).unwrap().unwrap());
+        assert!(region.read_chunk([4, 4, 4]).unwrap().is_none());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn grown_chunk_is_appended_and_its_entry_rewritten() {
+        let dir = test_dir("grown");
+        let path = dir.join("r.0.0.0.region");
+        let position = [2, 0, 1];
+        let slot = RegionFile::slot(position);
+
+        let mut region = RegionFile::open(&path).unwrap();
+        region.write_chunk(&Chunk::new(position)).unwrap();
+        let (old_offset, old_length) = region.offsets[slot];
+        assert_eq!(old_offset as u64, HEADER_SIZE);
+
+        // another chunk after it, so the grown chunk can't just spill over the end of the file
+        region.write_chunk(&Chunk::new([3, 0, 1])).unwrap();
+        let file_len = region.file.metadata().unwrap().len();
+
+        let grown = mixed_chunk(position);
+        region.write_chunk(&grown).unwrap();
+        let (new_offset, new_length) = region.offsets[slot];
+        assert!(new_length > old_length);
+        assert_eq!(new_offset as u64, file_len);
+
+        // a smaller chunk goes back into the slot it already has
+        let mut shrunk = Chunk::new(position);
+        shrunk.generate_full("test:stone");
+        region.write_chunk(&shrunk).unwrap();
+        assert_eq!(region.offsets[slot].0, new_offset);
+        drop(region);
+
+        // the offset table on disk points at the latest data
+        let mut region = RegionFile::open(&path).unwrap();
+        assert_eq!(region.offsets[slot].0, new_offset);
+        assert_same_blocks(&shrunk, &region.read_chunk(position).unwrap().unwrap());
+        assert!(region.read_chunk([3, 0, 1]).unwrap().is_some());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
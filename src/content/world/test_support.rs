@@ -0,0 +1,46 @@
+//! Worlds and content for tests that need blocks but no assets or GPU
+
+use crate::content::block::block::{Block, BlockProperties, RenderLayer};
+use crate::content::world::chunks::chunk::Chunk;
+use crate::content::world::world::World;
+use crate::engine::core::content_loader::GameContent;
+use crate::utils::registry::Registry;
+use std::sync::Arc;
+
+pub const STONE: &str = "test:stone";
+/// Not solid and not opaque, like tall grass
+pub const PLANT: &str = "test:plant";
+
+pub fn content() -> GameContent {
+    let mut blocks = Registry::new();
+    blocks.register(STONE, Block { id: STONE.to_string(), ..Block::default() });
+    let plant = BlockProperties {
+        solid: false,
+        opaque: false,
+        render_layer: RenderLayer::Cutout,
+        ..BlockProperties::default()
+    };
+    blocks.register(PLANT, Block { id: PLANT.to_string(), properties: plant, ..Block::default() });
+
+    GameContent { blocks, block_model: Registry::new() }
+}
+
+/// A world with empty chunks from `min` to `max` (inclusive, in chunk coordinates)
+pub fn empty_world(min: [i32; 3], max: [i32; 3]) -> World {
+    let mut world = World::new();
+    for z in min[2]..=max[2] {
+        for y in min[1]..=max[1] {
+            for x in min[0]..=max[0] {
+                world.chunks.insert([x, y, z], Arc::new(Chunk::new([x, y, z])));
+            }
+        }
+    }
+    world
+}
+
+/// Fills a whole chunk of the world with one block
+pub fn fill_chunk(world: &mut World, chunk_pos: [i32; 3], block: &str) {
+    let mut chunk = Chunk::new(chunk_pos);
+    chunk.generate_full(block);
+    world.chunks.insert(chunk_pos, Arc::new(chunk));
+}
@@ -1,16 +1,32 @@
 use crate::content::block::block::Block;
 use crate::content::world::chunks::chunk::{CHUNK_SIZE, Chunk};
+use crate::content::world::chunks::chunk_mesh::ChunkNeighbors;
+use crate::content::world::generation::{FlatGenerator, WorldGenerator};
+use crate::content::world::lighting::{self, BlockLight, Lighting};
+use crate::content::world::region::RegionStorage;
 use crate::engine::core::content_loader::GameContent;
-use std::collections::HashMap;
+use rapidhash::RapidHashMap;
+use std::collections::{HashMap, HashSet};
 use std::io::Error;
+use std::path::PathBuf;
 use std::sync::Arc;
 use vulkano::memory::allocator::StandardMemoryAllocator;
 
-#[derive(Default)]
 pub struct World {
     pub chunks: HashMap<[i32; 3], Arc<Chunk>>,
     pub loaded_chunks: HashMap<[i32; 3], Arc<Chunk>>,
+    pub dirty_chunks: HashSet<[i32; 3]>, // chunks edited since their mesh was last built
+    pub unsaved_chunks: HashSet<[i32; 3]>, // chunks edited since they were last written to disk
+    pub storage: Option<RegionStorage>,   // None keeps the world in memory only
+    pub generator: Arc<dyn WorldGenerator>,
     pub memory_allocator: Option<Arc<StandardMemoryAllocator>>,
+    pub light_properties: Arc<RapidHashMap<String, BlockLight>>, // block ID -> opacity and emission
+}
+
+impl Default for World {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl World {
@@ -18,32 +34,25 @@ impl World {
         Self {
             chunks: HashMap::new(),
             loaded_chunks: HashMap::new(),
+            dirty_chunks: HashSet::new(),
+            unsaved_chunks: HashSet::new(),
+            storage: None,
+            generator: Arc::new(FlatGenerator),
             memory_allocator: None,
+            light_properties: Arc::new(RapidHashMap::default()),
         }
     }
 
     pub fn init(&mut self) {}
 
-    /// Creates or loads a chunk at the given chunk coordinates
-    pub fn get_chunk(&mut self, pos: [i32; 3], content: &GameContent) -> Result<Arc<Chunk>, Error> {
-        if let Some(chunk_data) = self.chunks.get(&pos) {
-            return Ok(chunk_data.clone());
-        }
-
-        let mut chunk = Chunk::new(pos);
-
-        // Fill bottom half with dirt from registry
-        if pos[1] > 0 {
-            chunk.generate_empty(content);
-        } else if pos[1] == 0 {
-            chunk.generate_flat("template:dirt", content);
-        } else {
-            chunk.generate_full("template:dirt", content);
+    /// Collects the six face-adjacent chunks of `pos` that have been generated so far
+    pub fn neighbors(&self, pos: [i32; 3]) -> ChunkNeighbors {
+        let mut neighbors = ChunkNeighbors::default();
+        for (slot, offset) in ChunkNeighbors::OFFSETS.iter().enumerate() {
+            let neighbor_pos = [pos[0] + offset[0], pos[1] + offset[1], pos[2] + offset[2]];
+            neighbors.chunks[slot] = self.chunks.get(&neighbor_pos).cloned();
         }
-
-        let chunk_arc = Arc::new(chunk);
-        self.chunks.insert(pos, chunk_arc.clone());
-        Ok(chunk_arc)
+        neighbors
     }
 
     /// Gets block in world space coordinates using the content registry
@@ -64,6 +73,113 @@ impl World {
             .and_then(|chunk| chunk.get_block(lx as usize, ly as usize, lz as usize, content))
     }
 
+    /// Gets the state key of the block in world space coordinates
+    pub fn get_block_state_world(&self, wx: i32, wy: i32, wz: i32) -> Option<&str> {
+        let (cx, lx) = Self::to_chunk_coord(wx);
+        let (cy, ly) = Self::to_chunk_coord(wy);
+        let (cz, lz) = Self::to_chunk_coord(wz);
+
+        self.chunks
+            .get(&[cx, cy, cz])
+            .map(|chunk| chunk.get_block_state(lx as usize, ly as usize, lz as usize))
+    }
+
+    /// Gets the sky and block light level in world space coordinates
+    pub fn get_light_world(&self, wx: i32, wy: i32, wz: i32) -> Option<[u8; 2]> {
+        let (cx, lx) = Self::to_chunk_coord(wx);
+        let (cy, ly) = Self::to_chunk_coord(wy);
+        let (cz, lz) = Self::to_chunk_coord(wz);
+
+        self.chunks.get(&[cx, cy, cz]).map(|chunk| {
+            let idx = Chunk::index(lx as usize, ly as usize, lz as usize);
+            [chunk.sky_light.get(idx), chunk.block_light.get(idx)]
+        })
+    }
+
+    /// Sets a block state in world space coordinates and marks the affected chunks dirty.
+    /// Chunks are shared as `Arc`s, so the chunk gets copied on write if something else
+    /// (like a mesh job) still holds a reference to it.
+    /// Returns `false` if the chunk containing the block hasn't been generated.
+    pub fn set_block_world(&mut self, wx: i32, wy: i32, wz: i32, block_state: &str) -> bool {
+        let (cx, lx) = Self::to_chunk_coord(wx);
+        let (cy, ly) = Self::to_chunk_coord(wy);
+        let (cz, lz) = Self::to_chunk_coord(wz);
+        let chunk_pos = [cx, cy, cz];
+        let local = [lx as usize, ly as usize, lz as usize];
+
+        let Some(chunk) = self.chunks.get_mut(&chunk_pos) else {
+            return false;
+        };
+        if chunk.get_block_state(local[0], local[1], local[2]) == block_state {
+            return true;
+        }
+
+        // loaded_chunks shares the same Arc, drop it first so make_mut doesn't copy for nothing
+        let was_loaded = self.loaded_chunks.remove(&chunk_pos).is_some();
+        Arc::make_mut(chunk).set_block(local[0], local[1], local[2], block_state);
+        if was_loaded {
+            self.loaded_chunks.insert(chunk_pos, chunk.clone());
+        }
+
+        self.mark_dirty(chunk_pos, local);
+        self.unsaved_chunks.insert(chunk_pos);
+
+        let mut lighting = Lighting::new(
+            &mut self.chunks,
+            &mut self.loaded_chunks,
+            &self.light_properties,
+        );
+        lighting.block_changed([wx, wy, wz]);
+        self.dirty_chunks.extend(lighting.finish());
+        true
+    }
+
+    /// Connects the light of a chunk that was just added to memory (and already lit on its own
+    /// with `lighting::light_chunk`) with its neighbors. The chunk and its face neighbors get
+    /// meshed for being new anyway, so only the other chunks the light reached are marked dirty.
+    fn light_new_chunk(&mut self, chunk_pos: [i32; 3]) {
+        let mut lighting = Lighting::new(
+            &mut self.chunks,
+            &mut self.loaded_chunks,
+            &self.light_properties,
+        );
+        lighting.light_new_chunk(chunk_pos);
+
+        for changed in lighting.finish() {
+            let distance: i32 = (0..3).map(|axis| (changed[axis] - chunk_pos[axis]).abs()).sum();
+            if distance > 1 {
+                self.dirty_chunks.insert(changed);
+            }
+        }
+    }
+
+    /// Marks a chunk dirty, along with the neighbors that touch the given local block
+    /// since their border faces depend on it
+    fn mark_dirty(&mut self, chunk_pos: [i32; 3], local: [usize; 3]) {
+        self.dirty_chunks.insert(chunk_pos);
+
+        for axis in 0..3 {
+            let step = if local[axis] == 0 {
+                -1
+            } else if local[axis] == CHUNK_SIZE - 1 {
+                1
+            } else {
+                continue;
+            };
+
+            let mut neighbor_pos = chunk_pos;
+            neighbor_pos[axis] += step;
+            if self.chunks.contains_key(&neighbor_pos) {
+                self.dirty_chunks.insert(neighbor_pos);
+            }
+        }
+    }
+
+    /// Takes the set of chunks that need remeshing, leaving it empty
+    pub fn take_dirty_chunks(&mut self) -> Vec<[i32; 3]> {
+        self.dirty_chunks.drain().collect()
+    }
+
     /// Converts world coordinate -> chunk plus local coordinate
     #[inline(always)]
     fn to_chunk_coord(world_coord: i32) -> (i32, i32) {
@@ -77,12 +193,96 @@ impl World {
         self.memory_allocator = Some(allocator);
     }
 
-    pub fn load_chunk(&mut self, chunk_pos: [i32; 3]) {
-        let chunk = self.get_chunk(chunk_pos, &GameContent::get()).unwrap();
+    /// Sets the opacity and light emission of every block, used when lighting chunks
+    pub fn set_light_properties(&mut self, light_properties: RapidHashMap<String, BlockLight>) {
+        self.light_properties = Arc::new(light_properties);
+    }
+
+    /// Replaces the generator used for chunks that haven't been generated yet
+    pub fn set_generator(&mut self, generator: Arc<dyn WorldGenerator>) {
+        self.generator = generator;
+    }
+
+    /// Persists the world's chunks as region files in the given directory
+    pub fn set_save_dir<P: Into<PathBuf>>(&mut self, dir: P) {
+        self.storage = Some(RegionStorage::new(dir));
+    }
+
+    /// Writes a chunk to disk if it was edited since it was last saved
+    pub fn save_chunk(&mut self, chunk_pos: [i32; 3]) -> Result<(), Error> {
+        if !self.unsaved_chunks.contains(&chunk_pos) {
+            return Ok(());
+        }
+        let (Some(storage), Some(chunk)) = (&mut self.storage, self.chunks.get(&chunk_pos)) else {
+            return Ok(());
+        };
+
+        storage.save_chunk(chunk)?;
+        self.unsaved_chunks.remove(&chunk_pos);
+        Ok(())
+    }
+
+    /// Writes every edited chunk to disk, e.g. before exiting
+    pub fn save_all(&mut self) -> Result<(), Error> {
+        let unsaved: Vec<[i32; 3]> = self.unsaved_chunks.iter().cloned().collect();
+        for chunk_pos in unsaved {
+            self.save_chunk(chunk_pos)?;
+        }
+        Ok(())
+    }
+
+    /// Loads a chunk that is already in memory or saved on disk without generating it.
+    /// Returns `false` if the chunk still has to be generated.
+    pub fn load_existing_chunk(&mut self, chunk_pos: [i32; 3]) -> bool {
+        let chunk = if let Some(chunk) = self.chunks.get(&chunk_pos) {
+            chunk.clone()
+        } else {
+            let Some(storage) = &mut self.storage else {
+                return false;
+            };
+            match storage.load_chunk(chunk_pos) {
+                Ok(Some(mut chunk)) => {
+                    lighting::light_chunk(&mut chunk, &self.light_properties);
+                    self.chunks.insert(chunk_pos, Arc::new(chunk));
+                    self.light_new_chunk(chunk_pos);
+                    self.chunks[&chunk_pos].clone()
+                }
+                Ok(None) => return false,
+                Err(e) => {
+                    eprintln!("Failed to load chunk {chunk_pos:?}, regenerating it: {e}");
+                    return false;
+                }
+            }
+        };
 
         self.loaded_chunks.insert(chunk_pos, chunk);
+        true
+    }
+
+    /// Adds a chunk that was generated outside the world (e.g. on a worker thread) and loads it.
+    /// The chunk has to be lit with `lighting::light_chunk` already.
+    pub fn insert_generated_chunk(&mut self, chunk: Chunk) -> Arc<Chunk> {
+        let chunk_pos = chunk.position;
+        self.chunks.insert(chunk_pos, Arc::new(chunk));
+        self.light_new_chunk(chunk_pos);
+
+        let chunk_arc = self.chunks[&chunk_pos].clone();
+        self.loaded_chunks.insert(chunk_pos, chunk_arc.clone());
+        chunk_arc
     }
+
     pub fn unload_chunk(&mut self, chunk_pos: [i32; 3]) {
         let _ = self.loaded_chunks.remove(&chunk_pos);
+
+        // With a save directory the chunk can come back from disk (or be regenerated if it was
+        // never edited), so it doesn't need to stay in memory. Keep it if saving failed.
+        if self.storage.is_some() {
+            match self.save_chunk(chunk_pos) {
+                Ok(()) => {
+                    self.chunks.remove(&chunk_pos);
+                }
+                Err(e) => eprintln!("Failed to save chunk {chunk_pos:?}: {e}"),
+            }
+        }
     }
 }
@@ -1,4 +1,5 @@
 pub mod core;
+pub mod ecs;
 pub mod rendering;
 pub mod ui;
 mod scenes;
@@ -2,3 +2,4 @@ pub mod app;
 pub mod input;
 pub mod user_settings;
 pub mod content_loader;
+pub mod fixed_timestep;
@@ -1,5 +1,5 @@
 use rapidhash::RapidHashMap;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::ops::Not;
 use std::sync::Arc;
 use std::time::{self, Instant};
@@ -26,8 +26,8 @@ use vulkano::{
     pipeline::{
         GraphicsPipeline, PipelineLayout, PipelineShaderStageCreateInfo,
         graphics::{
-            color_blend::{ColorBlendAttachmentState, ColorBlendState},
-            input_assembly::InputAssemblyState,
+            color_blend::{AttachmentBlend, ColorBlendAttachmentState, ColorBlendState},
+            input_assembly::{InputAssemblyState, PrimitiveTopology},
             multisample::MultisampleState,
             rasterization::RasterizationState,
             vertex_input::{Vertex, VertexDefinition},
@@ -37,7 +37,7 @@ use vulkano::{
     },
     render_pass::Subpass,
     swapchain::{self},
-    sync::{self, GpuFuture},
+    sync::{self, GpuFuture, future::FenceSignalFuture},
 };
 use winit::event::{DeviceEvent, DeviceId};
 use winit::window::CursorGrabMode;
@@ -46,10 +46,12 @@ use winit::{
     window::Window,
 };
 
+use crate::engine::core::fixed_timestep::FixedTimestep;
 use crate::engine::core::input::{InputState, KeyBind};
 use crate::engine::core::user_settings::UserSettings;
+use crate::engine::rendering::gpu_frames::GPU_FRAMES;
 use crate::engine::rendering::mvp::MVP;
-use crate::engine::rendering::vertex::BlockVertex;
+use crate::engine::rendering::vertex::{BlockVertex, ChunkVertex, EntityInstance, OutlineInstance};
 use crate::engine::rendering::vswapchain::{create_swapchain, window_size_dependent_setup};
 use crate::engine::rendering::vulkan::{create_device_and_queue, create_instance};
 use crate::engine::rendering::{
@@ -61,7 +63,8 @@ use crate::engine::ui::egui_integration::EguiStruct;
 
 use crate::content::block::block::Block;
 use crate::engine::core::content_loader::GameContent;
-use crate::engine::rendering::texture_atlas::{AtlasUV, TextureAtlas};
+use crate::engine::rendering::baked_model::BakedModel;
+use crate::engine::rendering::texture_atlas::TextureAtlas;
 use crate::engine::resources::load_json5_dir;
 use crate::engine::scenes::handling::scene_trait::SceneResources;
 use crate::utils::registry::Registry;
@@ -74,6 +77,12 @@ use vulkano::image::ImageUsage;
 use vulkano::image::sampler::{Filter, Sampler, SamplerCreateInfo};
 use vulkano::image::view::ImageView;
 
+/// Most fixed updates a single frame runs before it gives up on catching up
+const MAX_TICKS_PER_FRAME: u32 = 5;
+
+/// Fence a submitted frame signals once the GPU is done with it
+type FrameFence = FenceSignalFuture<Box<dyn GpuFuture>>;
+
 //
 // `App` holds the state of the application, including all Vulkan objects that need to persist between frames.
 pub struct App {
@@ -88,8 +97,13 @@ pub struct App {
     framebuffers: Option<Vec<Arc<vulkano::render_pass::Framebuffer>>>,
     recreate_swapchain: bool,
     previous_frame_end: Option<Box<dyn GpuFuture>>,
+    frames_in_flight: VecDeque<(u64, Arc<FrameFence>)>, // frame number, fence
     command_buffer_allocator: Option<Arc<StandardCommandBufferAllocator>>,
     pipeline: Option<Arc<GraphicsPipeline>>,
+    cutout_pipeline: Option<Arc<GraphicsPipeline>>,
+    translucent_pipeline: Option<Arc<GraphicsPipeline>>,
+    outline_pipeline: Option<Arc<GraphicsPipeline>>,
+    entity_pipeline: Option<Arc<GraphicsPipeline>>,
     egui: Option<EguiStruct>,
     mvp_buffer: Option<Subbuffer<MVP>>,
     descriptor_set_allocator: Option<Arc<StandardDescriptorSetAllocator>>,
@@ -99,12 +113,13 @@ pub struct App {
     input_state: Option<InputState>,
     user_settings: Option<UserSettings>,
     last_frame_time: Option<std::time::Instant>,
+    fixed_timestep: Option<FixedTimestep>,
     capture_cursor: bool,
     scene_manager: Option<SceneManager>, // MAIN GAME SCENE MANAGER
     texture: Option<Arc<vulkano::image::view::ImageView>>,
     sampler: Option<Arc<vulkano::image::sampler::Sampler>>,
     pub content: Option<Arc<GameContent>>,
-    pub block_uvs: Option<RapidHashMap<String, AtlasUV>>,
+    pub block_models: Option<RapidHashMap<String, BakedModel>>,
 }
 
 impl Default for App {
@@ -125,8 +140,13 @@ impl Default for App {
             framebuffers: None,
             recreate_swapchain: false,
             previous_frame_end: None,
+            frames_in_flight: VecDeque::new(),
             command_buffer_allocator: None,
             pipeline: None,
+            cutout_pipeline: None,
+            translucent_pipeline: None,
+            outline_pipeline: None,
+            entity_pipeline: None,
             egui: None,
             mvp_buffer: None,
             descriptor_set_allocator: None,
@@ -136,12 +156,13 @@ impl Default for App {
             input_state: None,
             user_settings: None,
             last_frame_time: None,
+            fixed_timestep: None,
             capture_cursor: true,
             scene_manager: None, // MAIN GAME SCENE MANAGER
             sampler: None,
             texture: None,
             content: None,
-            block_uvs: None,
+            block_models: None,
         }
     }
 }
@@ -198,7 +219,7 @@ impl App {
         );
         self.texture = Some(atlas.image_view.clone());
         self.sampler = Some(atlas.sampler.clone());
-        self.block_uvs = Some(atlas.block_uvs.clone());
+        self.block_models = Some(atlas.block_models.clone());
 
         self.camera = Some(Camera::from_pos(2.5, 22.5, -2.5));
 
@@ -239,6 +260,10 @@ impl App {
 
         // UserSettings is a singleton in order for easy access anywhere and hot reloading
         self.user_settings = Some(UserSettings::instance());
+        self.fixed_timestep = Some(FixedTimestep::new(
+            UserSettings::instance().tick_rate,
+            MAX_TICKS_PER_FRAME,
+        ));
     }
     fn create_pipeline(&mut self) {
         let depth_stencil_state = DepthStencilState {
@@ -256,11 +281,20 @@ impl App {
         mod fs {
             vulkano_shaders::shader! { ty: "fragment", path: "assets/shaders/first_triangle/fragment.glsl" }
         }
+        mod cutout_fs {
+            vulkano_shaders::shader! {
+                ty: "fragment",
+                path: "assets/shaders/first_triangle/fragment.glsl",
+                define: [("ALPHA_TEST", "1")],
+            }
+        }
 
         let vs = vs::load(self.device.as_ref().unwrap().clone())
             .expect("failed to create shader module");
         let fs = fs::load(self.device.as_ref().unwrap().clone())
             .expect("failed to create shader module");
+        let cutout_fs = cutout_fs::load(self.device.as_ref().unwrap().clone())
+            .expect("failed to create shader module");
 
         let viewport = Viewport {
             offset: [0.0, 0.0],
@@ -268,12 +302,14 @@ impl App {
             depth_range: 0.0..=1.0,
         };
 
-        // Creating the graphics pipeline
-        let pipeline = {
+        // Creating the graphics pipelines, one per render layer. They share one layout so the
+        // descriptor set bound for the frame stays valid when switching between them.
+        let (pipeline, cutout_pipeline, translucent_pipeline) = {
             let vs_entry = vs.entry_point("main").unwrap();
             let fs_entry = fs.entry_point("main").unwrap();
+            let cutout_fs_entry = cutout_fs.entry_point("main").unwrap();
 
-            let vertex_input_state = BlockVertex::per_vertex().definition(&vs_entry).unwrap();
+            let vertex_input_state = ChunkVertex::per_vertex().definition(&vs_entry).unwrap();
 
             let stages = [
                 PipelineShaderStageCreateInfo::new(vs_entry),
@@ -288,6 +324,152 @@ impl App {
             )
             .unwrap();
 
+            let subpass = Subpass::from(self.render_pass.as_ref().unwrap().clone(), 0).unwrap();
+            let color_attachments = subpass.num_color_attachments();
+
+            let opaque_info = GraphicsPipelineCreateInfo {
+                stages: stages.iter().cloned().collect(),
+                vertex_input_state: Some(vertex_input_state),
+                depth_stencil_state: Some(depth_stencil_state.clone()),
+                input_assembly_state: Some(InputAssemblyState::default()),
+                viewport_state: Some(ViewportState {
+                    viewports: [viewport.clone()].into_iter().collect(),
+                    ..Default::default()
+                }),
+                rasterization_state: Some(RasterizationState {
+                    cull_mode: vulkano::pipeline::graphics::rasterization::CullMode::Back,
+                    ..Default::default()
+                }),
+                multisample_state: Some(MultisampleState::default()),
+                color_blend_state: Some(ColorBlendState::with_attachment_states(
+                    color_attachments,
+                    ColorBlendAttachmentState::default(),
+                )),
+                subpass: Some(subpass.into()),
+                ..GraphicsPipelineCreateInfo::layout(layout)
+            };
+
+            // discards transparent pixels, otherwise the same as opaque
+            let cutout_info = GraphicsPipelineCreateInfo {
+                stages: [
+                    stages[0].clone(),
+                    PipelineShaderStageCreateInfo::new(cutout_fs_entry),
+                ]
+                .into_iter()
+                .collect(),
+                ..opaque_info.clone()
+            };
+
+            // blends over what's already drawn, and doesn't write depth so translucent faces
+            // behind other translucent faces still get drawn
+            let translucent_info = GraphicsPipelineCreateInfo {
+                depth_stencil_state: Some(DepthStencilState {
+                    depth: Some(vulkano::pipeline::graphics::depth_stencil::DepthState {
+                        write_enable: false,
+                        compare_op: vulkano::pipeline::graphics::depth_stencil::CompareOp::Less,
+                    }),
+                    ..Default::default()
+                }),
+                color_blend_state: Some(ColorBlendState::with_attachment_states(
+                    color_attachments,
+                    ColorBlendAttachmentState {
+                        blend: Some(AttachmentBlend::alpha()),
+                        ..Default::default()
+                    },
+                )),
+                ..opaque_info.clone()
+            };
+
+            let create = |info| {
+                GraphicsPipeline::new(self.device.as_ref().unwrap().clone(), None, info).unwrap()
+            };
+            (create(opaque_info), create(cutout_info), create(translucent_info))
+        };
+        self.pipeline = Some(pipeline.clone()); // store
+        self.cutout_pipeline = Some(cutout_pipeline);
+        self.translucent_pipeline = Some(translucent_pipeline);
+
+        // Line pipeline for the block selection outline. Uses the default pipeline's layout so
+        // the MVP descriptor set bound for the frame stays valid when switching to it. The
+        // outline vertices never change, each instance moves them onto the outlined block.
+        mod outline_vs {
+            vulkano_shaders::shader! { ty: "vertex", path: "assets/shaders/outline/vertex.glsl" }
+        }
+        mod outline_fs {
+            vulkano_shaders::shader! { ty: "fragment", path: "assets/shaders/outline/fragment.glsl" }
+        }
+
+        let outline_vs = outline_vs::load(self.device.as_ref().unwrap().clone())
+            .expect("failed to create shader module");
+        let outline_fs = outline_fs::load(self.device.as_ref().unwrap().clone())
+            .expect("failed to create shader module");
+
+        let outline_pipeline = {
+            let vs_entry = outline_vs.entry_point("main").unwrap();
+            let fs_entry = outline_fs.entry_point("main").unwrap();
+
+            let vertex_input_state = [BlockVertex::per_vertex(), OutlineInstance::per_instance()]
+                .definition(&vs_entry)
+                .unwrap();
+
+            let stages = [
+                PipelineShaderStageCreateInfo::new(vs_entry),
+                PipelineShaderStageCreateInfo::new(fs_entry),
+            ];
+
+            let subpass = Subpass::from(self.render_pass.as_ref().unwrap().clone(), 0).unwrap();
+
+            GraphicsPipeline::new(
+                self.device.as_ref().unwrap().clone(),
+                None,
+                GraphicsPipelineCreateInfo {
+                    stages: stages.into_iter().collect(),
+                    vertex_input_state: Some(vertex_input_state),
+                    depth_stencil_state: Some(depth_stencil_state.clone()),
+                    input_assembly_state: Some(InputAssemblyState {
+                        topology: PrimitiveTopology::LineList,
+                        ..Default::default()
+                    }),
+                    viewport_state: Some(ViewportState {
+                        viewports: [viewport.clone()].into_iter().collect(),
+                        ..Default::default()
+                    }),
+                    rasterization_state: Some(RasterizationState::default()),
+                    multisample_state: Some(MultisampleState::default()),
+                    color_blend_state: Some(ColorBlendState::with_attachment_states(
+                        subpass.num_color_attachments(),
+                        ColorBlendAttachmentState::default(),
+                    )),
+                    subpass: Some(subpass.into()),
+                    ..GraphicsPipelineCreateInfo::layout(pipeline.layout().clone())
+                },
+            )
+            .unwrap()
+        };
+        self.outline_pipeline = Some(outline_pipeline);
+
+        // Instanced pipeline for entity models. Each instance brings its own model matrix, and the
+        // cutout fragment shader is reused so entities sample the atlas and get lit like blocks.
+        mod entity_vs {
+            vulkano_shaders::shader! { ty: "vertex", path: "assets/shaders/entity/vertex.glsl" }
+        }
+
+        let entity_vs = entity_vs::load(self.device.as_ref().unwrap().clone())
+            .expect("failed to create shader module");
+
+        let entity_pipeline = {
+            let vs_entry = entity_vs.entry_point("main").unwrap();
+            let fs_entry = cutout_fs.entry_point("main").unwrap();
+
+            let vertex_input_state = [BlockVertex::per_vertex(), EntityInstance::per_instance()]
+                .definition(&vs_entry)
+                .unwrap();
+
+            let stages = [
+                PipelineShaderStageCreateInfo::new(vs_entry),
+                PipelineShaderStageCreateInfo::new(fs_entry),
+            ];
+
             let subpass = Subpass::from(self.render_pass.as_ref().unwrap().clone(), 0).unwrap();
 
             GraphicsPipeline::new(
@@ -312,12 +494,12 @@ impl App {
                         ColorBlendAttachmentState::default(),
                     )),
                     subpass: Some(subpass.into()),
-                    ..GraphicsPipelineCreateInfo::layout(layout)
+                    ..GraphicsPipelineCreateInfo::layout(pipeline.layout().clone())
                 },
             )
             .unwrap()
         };
-        self.pipeline = Some(pipeline.clone()); // store
+        self.entity_pipeline = Some(entity_pipeline);
 
         let descriptor_set_allocator = Arc::new(StandardDescriptorSetAllocator::new(
             self.device.as_ref().unwrap().clone(),
@@ -379,10 +561,15 @@ impl ApplicationHandler for App {
         let scene_manager = SceneManager::instance();
         let resources = SceneResources {
             memory_allocator: self.memory_allocator.as_ref().unwrap().clone(),
+            descriptor_set_allocator: self.descriptor_set_allocator.as_ref().unwrap().clone(),
             default_pipeline: self.pipeline.as_ref().unwrap().clone(),
+            cutout_pipeline: self.cutout_pipeline.as_ref().unwrap().clone(),
+            translucent_pipeline: self.translucent_pipeline.as_ref().unwrap().clone(),
+            outline_pipeline: self.outline_pipeline.as_ref().unwrap().clone(),
+            entity_pipeline: self.entity_pipeline.as_ref().unwrap().clone(),
             texture: self.texture.as_ref().unwrap().clone(),
             sampler: self.sampler.as_ref().unwrap().clone(),
-            block_uvs: self.block_uvs.clone(), // passes atlas UVs
+            block_models: self.block_models.clone(), // passes baked block models
         };
         scene_manager.set_scene_resources(resources);
         scene_manager.awake();
@@ -465,6 +652,9 @@ impl ApplicationHandler for App {
 
         match event {
             WindowEvent::CloseRequested => {
+                if let Some(scene_manager) = &self.scene_manager {
+                    scene_manager.exit();
+                }
                 event_loop.exit();
             }
             // On resize, simply flag that the swapchain needs to be recreated.
@@ -482,13 +672,19 @@ impl ApplicationHandler for App {
                 };
                 self.last_frame_time = Some(now);
 
-                // Scene update order
-                if let Some(scene_manager) = &self.scene_manager {
+                // Scene update order. Fixed updates run as many whole ticks as the frame time
+                // adds up to, so the simulation doesn't depend on the frame rate
+                if let (Some(scene_manager), Some(fixed_timestep)) =
+                    (&self.scene_manager, self.fixed_timestep.as_mut())
+                {
                     if let (Some(input_state), Some(camera)) =
                         (self.input_state.as_mut(), self.camera.as_mut())
                     {
-                        scene_manager.fixed_update(delta_time, input_state, camera);
-                        scene_manager.update(delta_time, input_state, camera);
+                        for _ in 0..fixed_timestep.advance(delta_time) {
+                            scene_manager.fixed_update(fixed_timestep.step(), input_state, camera);
+                        }
+                        let alpha = fixed_timestep.alpha();
+                        scene_manager.update(delta_time, alpha, input_state, camera);
                         scene_manager.late_update(delta_time, input_state, camera);
                     }
                 }
@@ -614,6 +810,14 @@ impl ApplicationHandler for App {
 
                 // Ensure the GPU resources from N-2 frames ago are freed
                 previous_frame_end.cleanup_finished();
+                // Frames whose fence signaled are done reading their resources
+                while let Some((frame, fence)) = self.frames_in_flight.front() {
+                    if !fence.is_signaled().unwrap_or(false) {
+                        break;
+                    }
+                    GPU_FRAMES.complete(*frame);
+                    self.frames_in_flight.pop_front();
+                }
 
                 // Acquire the next available image from the swapchain.
                 let (image_i, suboptimal, acquire_future) =
@@ -673,10 +877,15 @@ impl ApplicationHandler for App {
                     .unwrap();
 
                 // Do scene manager lifecycle draw
-                if let (Some(scene_manager), Some(memory_allocator)) =
-                    (&self.scene_manager, &self.memory_allocator)
+                if let (Some(scene_manager), Some(memory_allocator), Some(fixed_timestep)) =
+                    (&self.scene_manager, &self.memory_allocator, &self.fixed_timestep)
                 {
-                    scene_manager.draw(&mut cmd_buffer_builder, &self.viewport, memory_allocator);
+                    scene_manager.draw(
+                        &mut cmd_buffer_builder,
+                        &self.viewport,
+                        memory_allocator,
+                        fixed_timestep.alpha(),
+                    );
                 }
 
                 cmd_buffer_builder
@@ -712,20 +921,32 @@ impl ApplicationHandler for App {
                             image_i,
                         ),
                     )
+                    .boxed()
                     .then_signal_fence_and_flush();
+                let frame = GPU_FRAMES.submit();
 
                 // Handle the result of the submission.
+                // A failed submission waits for the queue to go idle when it's dropped, so all
+                // frames up to this one are finished then.
                 match future.map_err(Validated::unwrap) {
                     Ok(future) => {
+                        // only ever used on this thread, the Arc is what makes it a `GpuFuture`
+                        #[allow(clippy::arc_with_non_send_sync)]
+                        let future = Arc::new(future);
+                        self.frames_in_flight.push_back((frame, future.clone()));
                         self.previous_frame_end = Some(future.boxed());
                     }
                     Err(VulkanError::OutOfDate) => {
                         self.recreate_swapchain = true;
                         self.previous_frame_end = Some(sync::now(device.clone()).boxed());
+                        self.frames_in_flight.clear();
+                        GPU_FRAMES.complete(frame);
                     }
                     Err(e) => {
                         println!("failed to flush future: {e}");
                         self.previous_frame_end = Some(sync::now(device.clone()).boxed());
+                        self.frames_in_flight.clear();
+                        GPU_FRAMES.complete(frame);
                     }
                 }
                 self.input_state
@@ -0,0 +1,100 @@
+/// Turns variable frame times into a whole number of fixed ticks. Frame time piles up in an
+/// accumulator and every full tick's worth of it is spent on one fixed update, so the simulation
+/// sees the same steps no matter how fast frames are drawn.
+#[derive(Clone, Copy, Debug)]
+pub struct FixedTimestep {
+    step: f32,
+    accumulator: f32,
+    max_steps: u32,
+}
+
+impl FixedTimestep {
+    /// `tick_rate` is in ticks per second. At most `max_steps` ticks run per frame, so a slow
+    /// frame can't make the next one even slower by queueing more ticks than it can catch up on.
+    pub fn new(tick_rate: u32, max_steps: u32) -> Self {
+        Self {
+            step: 1.0 / tick_rate.max(1) as f32,
+            accumulator: 0.0,
+            max_steps: max_steps.max(1),
+        }
+    }
+
+    /// Length of one tick in seconds
+    pub fn step(&self) -> f32 {
+        self.step
+    }
+
+    /// Adds a frame's time and returns how many ticks to run for it. Time past the last tick
+    /// that fits under `max_steps` is dropped, which slows the simulation down instead of
+    /// letting it fall further and further behind.
+    pub fn advance(&mut self, frame_time: f32) -> u32 {
+        self.accumulator += frame_time;
+        let mut steps = 0;
+        while self.accumulator >= self.step && steps < self.max_steps {
+            self.accumulator -= self.step;
+            steps += 1;
+        }
+        if self.accumulator >= self.step {
+            self.accumulator %= self.step;
+        }
+        steps
+    }
+
+    /// How far the time left over after the last tick is into the next one, from 0 to 1.
+    /// Used to blend between the previous and the current tick's state when drawing.
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / self.step).min(1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TICK_RATE: u32 = 20;
+
+    /// Runs `frames` frames of `frame_time` and returns the ticks run after each one
+    fn run(timestep: &mut FixedTimestep, frame_time: f32, frames: u32) -> Vec<u32> {
+        (0..frames).map(|_| timestep.advance(frame_time)).collect()
+    }
+
+    #[test]
+    fn same_time_gives_same_ticks_at_any_frame_rate() {
+        for seconds in [1, 3, 60] {
+            let mut slow = FixedTimestep::new(TICK_RATE, 8);
+            let mut fast = FixedTimestep::new(TICK_RATE, 8);
+            let slow_ticks: u32 = run(&mut slow, 1.0 / 20.0, 20 * seconds).iter().sum();
+            let fast_ticks = run(&mut fast, 1.0 / 140.0, 140 * seconds);
+
+            assert_eq!(slow_ticks, TICK_RATE * seconds);
+            assert_eq!(fast_ticks.iter().sum::<u32>(), TICK_RATE * seconds);
+            // one tick every seven frames, never two at once
+            assert!(fast_ticks.chunks(7).all(|frames| frames.iter().sum::<u32>() == 1));
+        }
+    }
+
+    #[test]
+    fn max_steps_caps_ticks_and_drops_the_rest() {
+        let mut timestep = FixedTimestep::new(TICK_RATE, 4);
+        assert_eq!(timestep.advance(1.0), 4);
+        // the time for the other 16 ticks is gone, not run on later frames
+        assert!(timestep.alpha() < 1.0);
+        assert_eq!(timestep.advance(0.0), 0);
+        assert_eq!(timestep.advance(timestep.step()), 1);
+    }
+
+    #[test]
+    fn alpha_stays_between_zero_and_one() {
+        let mut timestep = FixedTimestep::new(TICK_RATE, 3);
+        assert_eq!(timestep.alpha(), 0.0);
+        for frame_time in [0.001, 0.013, 0.05, 0.049, 0.4, 0.0, 0.02, 2.5, 0.0333] {
+            timestep.advance(frame_time);
+            let alpha = timestep.alpha();
+            assert!((0.0..=1.0).contains(&alpha), "alpha {alpha} after a {frame_time} s frame");
+        }
+
+        let mut timestep = FixedTimestep::new(TICK_RATE, 3);
+        timestep.advance(timestep.step() / 4.0);
+        assert!((timestep.alpha() - 0.25).abs() < 1e-5);
+    }
+}
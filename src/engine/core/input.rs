@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 
 use fixedstr::zstr;
-use winit::event::{ElementState, WindowEvent};
+use winit::event::{ElementState, MouseButton, WindowEvent};
 use winit::keyboard::{KeyCode, PhysicalKey};
 
 use crate::engine::core::user_settings::UserSettings;
@@ -55,6 +55,7 @@ impl KeyBind {
 pub struct InputState {
     pub keybind_states: HashMap<KeyBind, KeyState>,
     pub key_states: HashMap<KeyCode, KeyBind>,
+    pub mouse_button_states: HashMap<MouseButton, KeyBind>,
     pub mouse_delta_x: f32,
     pub mouse_delta_y: f32,
 }
@@ -69,6 +70,11 @@ impl InputState {
                     self.update_keybind(*keybind, event.state);
                 }
             }
+            WindowEvent::MouseInput { state, button, .. } => {
+                if let Some(keybind) = self.mouse_button_states.get(&button) {
+                    self.update_keybind(*keybind, state);
+                }
+            }
             _ => {}
         }
         //println!("{:?}", self.keybind_states);
@@ -126,27 +132,40 @@ impl Default for InputState {
     fn default() -> Self {
         let mut keybind_map: HashMap<KeyBind, KeyState> = HashMap::new();
         let mut key_map: HashMap<KeyCode, KeyBind> = HashMap::new();
+        let mut mouse_button_map: HashMap<MouseButton, KeyBind> = HashMap::new();
 
         keybind_map.insert(KeyBind::new("FreeMouse"), KeyState::Unpressed);
-        keybind_map.insert(KeyBind::new("DbgForward"), KeyState::Unpressed);
-        keybind_map.insert(KeyBind::new("DbgBackward"), KeyState::Unpressed);
-        keybind_map.insert(KeyBind::new("DbgLeft"), KeyState::Unpressed);
-        keybind_map.insert(KeyBind::new("DbgRight"), KeyState::Unpressed);
-        keybind_map.insert(KeyBind::new("DbgUp"), KeyState::Unpressed);
-        keybind_map.insert(KeyBind::new("DbgDown"), KeyState::Unpressed);
+        keybind_map.insert(KeyBind::new("MoveForward"), KeyState::Unpressed);
+        keybind_map.insert(KeyBind::new("MoveBackward"), KeyState::Unpressed);
+        keybind_map.insert(KeyBind::new("MoveLeft"), KeyState::Unpressed);
+        keybind_map.insert(KeyBind::new("MoveRight"), KeyState::Unpressed);
+        keybind_map.insert(KeyBind::new("MoveUp"), KeyState::Unpressed);
+        keybind_map.insert(KeyBind::new("MoveDown"), KeyState::Unpressed);
+        keybind_map.insert(KeyBind::new("Sprint"), KeyState::Unpressed);
+        keybind_map.insert(KeyBind::new("ToggleNoclip"), KeyState::Unpressed);
+        keybind_map.insert(KeyBind::new("BreakBlock"), KeyState::Unpressed);
+        keybind_map.insert(KeyBind::new("PlaceBlock"), KeyState::Unpressed);
+        keybind_map.insert(KeyBind::new("PickBlock"), KeyState::Unpressed);
 
         key_map.insert(KeyCode::Escape, KeyBind::new("FreeMouse"));
-        key_map.insert(KeyCode::KeyW, KeyBind::new("DbgForward"));
-        key_map.insert(KeyCode::KeyS, KeyBind::new("DbgBackward"));
-        key_map.insert(KeyCode::KeyA, KeyBind::new("DbgLeft"));
-        key_map.insert(KeyCode::KeyD, KeyBind::new("DbgRight"));
-        key_map.insert(KeyCode::Space, KeyBind::new("DbgUp"));
-        key_map.insert(KeyCode::ShiftLeft, KeyBind::new("DbgDown"));
-        key_map.insert(KeyCode::ShiftRight, KeyBind::new("DbgDown"));
+        key_map.insert(KeyCode::KeyW, KeyBind::new("MoveForward"));
+        key_map.insert(KeyCode::KeyS, KeyBind::new("MoveBackward"));
+        key_map.insert(KeyCode::KeyA, KeyBind::new("MoveLeft"));
+        key_map.insert(KeyCode::KeyD, KeyBind::new("MoveRight"));
+        key_map.insert(KeyCode::Space, KeyBind::new("MoveUp"));
+        key_map.insert(KeyCode::ShiftLeft, KeyBind::new("MoveDown"));
+        key_map.insert(KeyCode::ShiftRight, KeyBind::new("MoveDown"));
+        key_map.insert(KeyCode::ControlLeft, KeyBind::new("Sprint"));
+        key_map.insert(KeyCode::KeyV, KeyBind::new("ToggleNoclip"));
+
+        mouse_button_map.insert(MouseButton::Left, KeyBind::new("BreakBlock"));
+        mouse_button_map.insert(MouseButton::Right, KeyBind::new("PlaceBlock"));
+        mouse_button_map.insert(MouseButton::Middle, KeyBind::new("PickBlock"));
 
         InputState {
             keybind_states: keybind_map,
             key_states: key_map,
+            mouse_button_states: mouse_button_map,
             mouse_delta_x: 0.0,
             mouse_delta_y: 0.0,
         }
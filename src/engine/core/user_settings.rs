@@ -13,10 +13,18 @@ pub struct UserSettings {
     pub mouse_sensitivity: f32,
     pub vertical_render_distance: u32,
     pub horizontal_render_distance: u32,
+    pub greedy_meshing: bool,
+    pub ambient_occlusion: bool,
+    pub lod_distance: u32,
+    pub tick_rate: u32,
 }
 
 const DEFAULT_MOUSE_SENSITIVITY: f32 = 100.0;
 const DEFAULT_RENDER_DISTANCE: u32 = 2;
+const DEFAULT_GREEDY_MESHING: bool = true;
+const DEFAULT_AMBIENT_OCCLUSION: bool = true;
+const DEFAULT_LOD_DISTANCE: u32 = 8;
+const DEFAULT_TICK_RATE: u32 = 60;
 
 impl UserSettings {
     pub fn instance() -> Self {
@@ -74,10 +82,60 @@ impl UserSettings {
                 DEFAULT_RENDER_DISTANCE
             });
 
+        let greedy_meshing: bool = data
+            .get("Greedy Meshing")
+            .unwrap_or(&DEFAULT_GREEDY_MESHING.to_string())
+            .parse::<bool>()
+            .unwrap_or_else(|_| -> bool {
+                println!(
+                    "failed to parse greedy_meshing from settings file {used_settings_file_path:?}"
+                );
+                DEFAULT_GREEDY_MESHING
+            });
+
+        let ambient_occlusion: bool = data
+            .get("Ambient Occlusion")
+            .unwrap_or(&DEFAULT_AMBIENT_OCCLUSION.to_string())
+            .parse::<bool>()
+            .unwrap_or_else(|_| -> bool {
+                println!(
+                    "failed to parse ambient_occlusion from settings file {used_settings_file_path:?}"
+                );
+                DEFAULT_AMBIENT_OCCLUSION
+            });
+
+        let lod_distance: u32 = data
+            .get("LOD Distance")
+            .unwrap_or(&DEFAULT_LOD_DISTANCE.to_string())
+            .parse::<u32>()
+            .unwrap_or_else(|_| -> u32 {
+                println!(
+                    "failed to parse lod_distance from settings file {used_settings_file_path:?}"
+                );
+                DEFAULT_LOD_DISTANCE
+            });
+
+        let tick_rate: u32 = data
+            .get("Tick Rate")
+            .unwrap_or(&DEFAULT_TICK_RATE.to_string())
+            .parse::<u32>()
+            .ok()
+            .filter(|&tick_rate| tick_rate > 0)
+            .unwrap_or_else(|| -> u32 {
+                println!(
+                    "failed to parse tick_rate from settings file {used_settings_file_path:?}"
+                );
+                DEFAULT_TICK_RATE
+            });
+
         UserSettings {
             mouse_sensitivity,
             vertical_render_distance,
             horizontal_render_distance,
+            greedy_meshing,
+            ambient_occlusion,
+            lod_distance,
+            tick_rate,
         }
     }
 }
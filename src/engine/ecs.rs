@@ -0,0 +1,4 @@
+pub mod components;
+pub mod ecs_world;
+pub mod sparse_set;
+pub mod systems;
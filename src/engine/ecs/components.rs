@@ -0,0 +1,61 @@
+use crate::utils::math::Aabb;
+use glam::{Mat4, Quat, Vec3};
+
+/// Where an entity is and how it's turned and sized
+#[derive(Clone, Copy, Debug)]
+pub struct Transform {
+    /// For entities with a `Collider`, the centre of the bottom of the collision box
+    pub position: Vec3,
+    /// `position` before the last fixed update, for drawing between updates
+    pub previous_position: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+impl Transform {
+    pub fn from_position(position: Vec3) -> Self {
+        Self {
+            position,
+            previous_position: position,
+            rotation: Quat::IDENTITY,
+            scale: Vec3::ONE,
+        }
+    }
+
+    /// Model matrix with the position `alpha` of the way from the previous fixed update to the
+    /// last one
+    pub fn interpolated_matrix(&self, alpha: f32) -> Mat4 {
+        let position = self.previous_position.lerp(self.position, alpha);
+        Mat4::from_scale_rotation_translation(self.scale, self.rotation, position)
+    }
+}
+
+/// Movement in blocks per second. Without a `Collider` the entity flies through everything.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Velocity(pub Vec3);
+
+/// Collision box that keeps a moving entity out of solid blocks and pulls it down with gravity
+#[derive(Clone, Copy, Debug)]
+pub struct Collider {
+    /// Full width, height and depth of the box
+    pub size: Vec3,
+    pub on_ground: bool,
+}
+
+impl Collider {
+    pub fn new(size: Vec3) -> Self {
+        Self { size, on_ground: false }
+    }
+
+    /// The box for an entity standing at `position`
+    pub fn aabb(&self, position: Vec3) -> Aabb {
+        let half_width = Vec3::new(self.size.x / 2.0, 0.0, self.size.z / 2.0);
+        Aabb::new(position - half_width, position + half_width.with_y(self.size.y))
+    }
+}
+
+/// Draws the entity with a baked model, looked up by the same key as the block models
+#[derive(Clone, Debug)]
+pub struct Renderable {
+    pub model: String,
+}
@@ -0,0 +1,127 @@
+use crate::engine::ecs::components::{Collider, Renderable, Transform, Velocity};
+use crate::engine::ecs::sparse_set::SparseSet;
+use rapidhash::RapidHashMap;
+use std::any::{Any, TypeId, type_name};
+use std::cell::{Ref, RefCell, RefMut};
+
+/// Anything that can be stored on an entity
+pub trait Component: Any + Send {}
+
+impl<T: Any + Send> Component for T {}
+
+/// Handle to an entity. The generation tells a despawned entity apart from a newer one that
+/// reuses its index, so stale handles stop finding components instead of finding the wrong ones.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Entity {
+    index: u32,
+    generation: u32,
+}
+
+impl Entity {
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+}
+
+/// A component storage of any type, so `EcsWorld` can keep them all in one map
+trait AnyStorage: Send {
+    fn remove_entity(&mut self, entity: Entity);
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<T: Component> AnyStorage for RefCell<SparseSet<T>> {
+    fn remove_entity(&mut self, entity: Entity) {
+        self.get_mut().remove(entity);
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Holds the entities of a scene and one `SparseSet` per component type.
+/// Each storage is borrowed on its own through `storage` and `storage_mut`, so a system can hold
+/// several of them at once and join them. Borrowing the same storage mutably twice panics.
+pub struct EcsWorld {
+    /// Current generation of every entity index, alive or not
+    generations: Vec<u32>,
+    /// Indices of despawned entities, reused by `spawn`
+    free: Vec<u32>,
+    storages: RapidHashMap<TypeId, Box<dyn AnyStorage>>,
+}
+
+impl EcsWorld {
+    /// Creates an empty world with the built-in components registered
+    pub fn new() -> Self {
+        let mut ecs = Self {
+            generations: Vec::new(),
+            free: Vec::new(),
+            storages: RapidHashMap::default(),
+        };
+        ecs.register::<Transform>();
+        ecs.register::<Velocity>();
+        ecs.register::<Collider>();
+        ecs.register::<Renderable>();
+        ecs
+    }
+
+    /// Makes a storage for `T`. Components are registered on their first insert as well, this is
+    /// only needed to query a component before anything has it.
+    pub fn register<T: Component>(&mut self) {
+        self.storages
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(RefCell::new(SparseSet::<T>::new())));
+    }
+
+    pub fn spawn(&mut self) -> Entity {
+        if let Some(index) = self.free.pop() {
+            return Entity {
+                index,
+                generation: self.generations[index as usize],
+            };
+        }
+        self.generations.push(0);
+        Entity {
+            index: self.generations.len() as u32 - 1,
+            generation: 0,
+        }
+    }
+
+    /// Removes the entity and all its components. Does nothing if it's already gone.
+    pub fn despawn(&mut self, entity: Entity) {
+        if !self.is_alive(entity) {
+            return;
+        }
+        for storage in self.storages.values_mut() {
+            storage.remove_entity(entity);
+        }
+        self.generations[entity.index as usize] += 1;
+        self.free.push(entity.index);
+    }
+
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        self.generations.get(entity.index as usize) == Some(&entity.generation)
+    }
+
+    /// Gives `entity` a component, replacing the one of the same type it had
+    pub fn insert<T: Component>(&mut self, entity: Entity, component: T) {
+        assert!(self.is_alive(entity), "inserted a component into a despawned entity");
+        self.register::<T>();
+        self.storage_mut::<T>().insert(entity, component);
+    }
+
+    pub fn storage<T: Component>(&self) -> Ref<'_, SparseSet<T>> {
+        self.cell::<T>().borrow()
+    }
+
+    pub fn storage_mut<T: Component>(&self) -> RefMut<'_, SparseSet<T>> {
+        self.cell::<T>().borrow_mut()
+    }
+
+    fn cell<T: Component>(&self) -> &RefCell<SparseSet<T>> {
+        self.storages
+            .get(&TypeId::of::<T>())
+            .and_then(|storage| storage.as_any().downcast_ref())
+            .unwrap_or_else(|| panic!("component {} is not registered", type_name::<T>()))
+    }
+}
@@ -0,0 +1,85 @@
+use crate::engine::ecs::ecs_world::Entity;
+
+/// Marks an entity index without a component in `SparseSet::sparse`
+const EMPTY: u32 = u32::MAX;
+
+/// Storage for one component type. Components sit packed in `values` so iterating over them
+/// is a walk over a plain array, and `sparse` maps an entity's index to its slot for lookups.
+/// Removing swaps the last component into the freed slot, so the order isn't stable.
+pub struct SparseSet<T> {
+    sparse: Vec<u32>,
+    entities: Vec<Entity>,
+    values: Vec<T>,
+}
+
+impl<T> SparseSet<T> {
+    pub fn new() -> Self {
+        Self {
+            sparse: Vec::new(),
+            entities: Vec::new(),
+            values: Vec::new(),
+        }
+    }
+
+    fn slot(&self, entity: Entity) -> Option<usize> {
+        let slot = *self.sparse.get(entity.index() as usize)?;
+        (slot != EMPTY && self.entities[slot as usize] == entity).then_some(slot as usize)
+    }
+
+    /// Gives `entity` the component, returning the one it replaced
+    pub fn insert(&mut self, entity: Entity, value: T) -> Option<T> {
+        if let Some(slot) = self.slot(entity) {
+            return Some(std::mem::replace(&mut self.values[slot], value));
+        }
+        let index = entity.index() as usize;
+        if index >= self.sparse.len() {
+            self.sparse.resize(index + 1, EMPTY);
+        }
+        self.sparse[index] = self.values.len() as u32;
+        self.entities.push(entity);
+        self.values.push(value);
+        None
+    }
+
+    pub fn remove(&mut self, entity: Entity) -> Option<T> {
+        let slot = self.slot(entity)?;
+        self.sparse[entity.index() as usize] = EMPTY;
+        self.entities.swap_remove(slot);
+        if let Some(moved) = self.entities.get(slot) {
+            self.sparse[moved.index() as usize] = slot as u32;
+        }
+        Some(self.values.swap_remove(slot))
+    }
+
+    pub fn get(&self, entity: Entity) -> Option<&T> {
+        self.slot(entity).map(|slot| &self.values[slot])
+    }
+
+    pub fn get_mut(&mut self, entity: Entity) -> Option<&mut T> {
+        self.slot(entity).map(|slot| &mut self.values[slot])
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, &T)> {
+        self.entities.iter().copied().zip(&self.values)
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Entity, &mut T)> {
+        self.entities.iter().copied().zip(&mut self.values)
+    }
+
+    /// Queries the entities that have both this component and one from `other`
+    pub fn join<'a, U>(
+        &'a self,
+        other: &'a SparseSet<U>,
+    ) -> impl Iterator<Item = (Entity, &'a T, &'a U)> {
+        self.iter().filter_map(|(entity, value)| Some((entity, value, other.get(entity)?)))
+    }
+
+    /// Like `join`, but this component can be changed
+    pub fn join_mut<'a, U>(
+        &'a mut self,
+        other: &'a SparseSet<U>,
+    ) -> impl Iterator<Item = (Entity, &'a mut T, &'a U)> {
+        self.iter_mut().filter_map(|(entity, value)| Some((entity, value, other.get(entity)?)))
+    }
+}
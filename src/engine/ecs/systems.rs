@@ -0,0 +1,158 @@
+use crate::content::world::collision::{GRAVITY, TERMINAL_VELOCITY};
+use crate::content::world::world::World;
+use crate::engine::core::content_loader::GameContent;
+use crate::engine::ecs::components::{Collider, Transform, Velocity};
+use crate::engine::ecs::ecs_world::EcsWorld;
+
+/// How fast entities with a `Collider` slow down while sliding on the ground, as a fraction of
+/// their horizontal speed per second
+const GROUND_DRAG: f32 = 8.0;
+
+/// What systems get to see besides the entities
+pub struct SystemContext<'a> {
+    pub delta_time: f32,
+    pub world: &'a World,
+    pub content: &'a GameContent,
+}
+
+pub type System = Box<dyn FnMut(&mut EcsWorld, &SystemContext) + Send>;
+
+/// The systems a scene runs over its entities, in the order they were added
+#[derive(Default)]
+pub struct Systems {
+    update: Vec<System>,
+    fixed_update: Vec<System>,
+}
+
+impl Systems {
+    /// Runs `system` every frame
+    pub fn add_update(
+        &mut self,
+        system: impl FnMut(&mut EcsWorld, &SystemContext) + Send + 'static,
+    ) {
+        self.update.push(Box::new(system));
+    }
+
+    /// Runs `system` every fixed tick. Anything that moves entities belongs here.
+    pub fn add_fixed_update(
+        &mut self,
+        system: impl FnMut(&mut EcsWorld, &SystemContext) + Send + 'static,
+    ) {
+        self.fixed_update.push(Box::new(system));
+    }
+
+    pub fn run_update(&mut self, ecs: &mut EcsWorld, context: &SystemContext) {
+        for system in &mut self.update {
+            system(ecs, context);
+        }
+    }
+
+    pub fn run_fixed_update(&mut self, ecs: &mut EcsWorld, context: &SystemContext) {
+        for system in &mut self.fixed_update {
+            system(ecs, context);
+        }
+    }
+}
+
+/// Built-in system moving entities by their `Velocity`. Entities with a `Collider` also fall,
+/// slide to a stop on the ground and are swept against solid blocks like the player.
+/// Add it before other fixed update systems, since it also records where every entity was
+/// before the tick for drawing in between ticks.
+pub fn physics(ecs: &mut EcsWorld, context: &SystemContext) {
+    let delta_time = context.delta_time;
+    let mut transforms = ecs.storage_mut::<Transform>();
+    let mut velocities = ecs.storage_mut::<Velocity>();
+    let mut colliders = ecs.storage_mut::<Collider>();
+
+    for (_, transform) in transforms.iter_mut() {
+        transform.previous_position = transform.position;
+    }
+
+    for (entity, Velocity(velocity)) in velocities.iter_mut() {
+        let Some(transform) = transforms.get_mut(entity) else {
+            continue;
+        };
+        let Some(collider) = colliders.get_mut(entity) else {
+            transform.position += *velocity * delta_time;
+            continue;
+        };
+
+        velocity.y = (velocity.y - GRAVITY * delta_time).max(-TERMINAL_VELOCITY);
+        if collider.on_ground {
+            let drag = (1.0 - GROUND_DRAG * delta_time).max(0.0);
+            velocity.x *= drag;
+            velocity.z *= drag;
+        }
+
+        let motion = *velocity * delta_time;
+        let aabb = collider.aabb(transform.position);
+        let (aabb, blocked) = context.world.sweep_aabb(aabb, motion, context.content);
+        let centre = (aabb.min + aabb.max) / 2.0;
+        transform.position = centre.with_y(aabb.min.y);
+        for (axis, blocked) in blocked.into_iter().enumerate() {
+            if blocked {
+                velocity[axis] = 0.0;
+            }
+        }
+        collider.on_ground = blocked[1] && motion.y < 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::content::world::test_support::{self, STONE};
+    use crate::engine::core::fixed_timestep::FixedTimestep;
+    use glam::Vec3;
+
+    /// Throws a box across a stone floor and runs the physics for `frames` frames of `frame_time`
+    fn throw(frame_time: f32, frames: u32) -> (Transform, Vec3, Collider) {
+        let content = test_support::content();
+        let mut world = test_support::empty_world([-1, -1, -1], [1, 1, 1]);
+        for z in -1..=1 {
+            for x in -1..=1 {
+                test_support::fill_chunk(&mut world, [x, -1, z], STONE);
+            }
+        }
+
+        let mut ecs = EcsWorld::new();
+        let entity = ecs.spawn();
+        ecs.insert(entity, Transform::from_position(Vec3::new(0.5, 3.0, 0.5)));
+        ecs.insert(entity, Velocity(Vec3::new(4.0, 5.0, -2.0)));
+        ecs.insert(entity, Collider::new(Vec3::splat(0.25)));
+
+        let mut systems = Systems::default();
+        systems.add_fixed_update(physics);
+        let mut timestep = FixedTimestep::new(20, 8);
+        for _ in 0..frames {
+            for _ in 0..timestep.advance(frame_time) {
+                let context = SystemContext {
+                    delta_time: timestep.step(),
+                    world: &world,
+                    content: &content,
+                };
+                systems.run_fixed_update(&mut ecs, &context);
+            }
+        }
+
+        let transform = *ecs.storage::<Transform>().get(entity).unwrap();
+        let Velocity(velocity) = *ecs.storage::<Velocity>().get(entity).unwrap();
+        let collider = *ecs.storage::<Collider>().get(entity).unwrap();
+        (transform, velocity, collider)
+    }
+
+    #[test]
+    fn physics_is_the_same_at_any_frame_rate() {
+        let (slow, slow_velocity, slow_collider) = throw(1.0 / 20.0, 40);
+        let (fast, fast_velocity, fast_collider) = throw(1.0 / 140.0, 280);
+
+        // landed and slid to a stop
+        assert_eq!(slow.position.y, 0.0);
+        assert!(slow.position.x > 1.0);
+        assert!(slow_collider.on_ground);
+
+        assert_eq!(slow.position, fast.position);
+        assert_eq!(slow_velocity, fast_velocity);
+        assert_eq!(slow_collider.on_ground, fast_collider.on_ground);
+    }
+}
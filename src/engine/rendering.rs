@@ -7,3 +7,8 @@ pub mod camera;
 pub(crate) mod descriptor_helpers;
 pub(crate) mod cube;
 pub(crate) mod texture_atlas;
+pub(crate) mod baked_model;
+pub(crate) mod render_stats;
+pub(crate) mod gpu_frames;
+pub(crate) mod mesh_arena;
+pub(crate) mod entity_mesh;
@@ -0,0 +1,226 @@
+use crate::content::block::block::{
+    BlockFace, BlockModel, BlockProperties, BlockState, ModelElement, ModelShape, RenderLayer,
+};
+use crate::engine::rendering::cube;
+use crate::engine::rendering::texture_atlas::{AtlasUV, BlockFaceUVs, MISSING_UV};
+use crate::engine::rendering::vertex::BlockVertex;
+use crate::utils::math::Vec3;
+use rapidhash::RapidHashMap;
+
+/// One face of a non-cube model, positioned in block space (`0..1` on every axis).
+/// The corners go around the quad, which is drawn as triangles (0, 1, 2) and (2, 3, 0).
+#[derive(Debug, Clone, Copy)]
+pub struct BakedQuad {
+    pub vertices: [BlockVertex; 4],
+    /// Side of the block whose neighbor hides this quad when it occludes its touching face
+    pub cullface: Option<BlockFace>,
+}
+
+/// What the chunk mesher draws for a block state
+#[derive(Debug, Clone)]
+pub enum BakedGeometry {
+    /// A full cube, which can be merged with its neighbors by the greedy mesher
+    Cube(BlockFaceUVs),
+    /// Any other model, added block by block
+    Quads(Vec<BakedQuad>),
+}
+
+/// A block state's model with its rotation applied and its textures looked up in the atlas
+#[derive(Debug, Clone)]
+pub struct BakedModel {
+    pub geometry: BakedGeometry,
+    /// Faces, indexed by `BlockFace`, that completely cover their side of the block.
+    /// Faces of neighboring blocks touching an occluding face can't be seen and get culled.
+    pub occludes: [bool; 6],
+    /// Which of the chunk's meshes the block's faces go into
+    pub render_layer: RenderLayer,
+}
+
+impl BakedModel {
+    /// Used for blocks without a model, a full cube showing the whole atlas
+    pub const MISSING: BakedModel = BakedModel {
+        geometry: BakedGeometry::Cube(BlockFaceUVs::MISSING),
+        occludes: [true; 6],
+        render_layer: RenderLayer::Opaque,
+    };
+
+    /// Air, nothing to draw and nothing hidden behind it
+    pub const EMPTY: BakedModel = BakedModel {
+        geometry: BakedGeometry::Quads(Vec::new()),
+        occludes: [false; 6],
+        render_layer: RenderLayer::Opaque,
+    };
+
+    /// Whether the block darkens the corners of faces next to it, only full opaque blocks do
+    pub fn casts_ambient_occlusion(&self) -> bool {
+        self.occludes.iter().all(|&occludes| occludes)
+    }
+
+    pub fn bake(
+        model: &BlockModel,
+        state: &BlockState,
+        properties: &BlockProperties,
+        texture_uvs: &RapidHashMap<String, AtlasUV>,
+    ) -> Self {
+        let (geometry, occludes) = Self::bake_geometry(model, state, texture_uvs);
+        Self {
+            geometry,
+            // see-through blocks hide nothing, even with full faces
+            occludes: if properties.opaque { occludes } else { [false; 6] },
+            render_layer: properties.render_layer,
+        }
+    }
+
+    fn bake_geometry(
+        model: &BlockModel,
+        state: &BlockState,
+        texture_uvs: &RapidHashMap<String, AtlasUV>,
+    ) -> (BakedGeometry, [bool; 6]) {
+        let atlas = |tex: Option<&String>| {
+            tex.and_then(|tex| texture_uvs.get(tex)).copied().unwrap_or(MISSING_UV)
+        };
+
+        if model.elements.is_empty() {
+            return match model.shape {
+                ModelShape::Cube => (
+                    BakedGeometry::Cube(BlockFaceUVs::from_state(model, state, texture_uvs)),
+                    [true; 6],
+                ),
+                ModelShape::Cross => (
+                    BakedGeometry::Quads(cross_quads(atlas(model.cross_texture()))),
+                    [false; 6],
+                ),
+            };
+        }
+
+        let mut quads = Vec::new();
+        let mut model_occludes = [false; 6];
+        for element in &model.elements {
+            let from = element.from.map(|c| c / 16.0);
+            let to = element.to.map(|c| c / 16.0);
+
+            for (&face, element_face) in &element.faces {
+                let atlas = atlas(model.element_face_texture(face, element_face));
+                quads.push(BakedQuad {
+                    vertices: element_face_vertices(face, from, to, element_face.uv, atlas),
+                    cullface: element_face.cullface,
+                });
+
+                if covers_face(element, face) {
+                    model_occludes[face as usize] = true;
+                }
+            }
+        }
+
+        // turn everything from model space into block space
+        let rotate_face = |face: BlockFace| {
+            BlockFace::from_normal(state.rotate(face.normal()))
+                .expect("block rotations are quarter turns")
+        };
+        for quad in &mut quads {
+            for vertex in &mut quad.vertices {
+                vertex.position = rotate_point(state, vertex.position);
+            }
+            quad.cullface = quad.cullface.map(rotate_face);
+        }
+
+        let mut occludes = [false; 6];
+        for face in BlockFace::ALL {
+            occludes[rotate_face(face) as usize] = model_occludes[face as usize];
+        }
+
+        (BakedGeometry::Quads(quads), occludes)
+    }
+}
+
+/// Whether an element's face lies on the side of the block and covers all of it
+fn covers_face(element: &ModelElement, face: BlockFace) -> bool {
+    let normal = face.normal();
+    (0..3).all(|axis| match normal[axis] {
+        1 => element.to[axis] >= 16.0,
+        -1 => element.from[axis] <= 0.0,
+        _ => element.from[axis] <= 0.0 && element.to[axis] >= 16.0,
+    })
+}
+
+/// Rotates a block space point around the block's centre
+fn rotate_point(state: &BlockState, point: Vec3) -> Vec3 {
+    let axes = [[1, 0, 0], [0, 1, 0], [0, 0, 1]].map(|axis| state.rotate(axis));
+
+    let mut rotated = [0.5; 3];
+    for (axis, direction) in axes.iter().enumerate() {
+        let offset = point.0[axis] - 0.5;
+        for i in 0..3 {
+            rotated[i] += offset * direction[i] as f32;
+        }
+    }
+    Vec3::from(rotated)
+}
+
+/// Corners of one face of the box from `from` to `to` (block space).
+/// The cube face template is squashed onto the box, which keeps its winding and texture axes.
+fn element_face_vertices(
+    face: BlockFace,
+    from: [f32; 3],
+    to: [f32; 3],
+    uv: Option<[f32; 4]>,
+    atlas: AtlasUV,
+) -> [BlockVertex; 4] {
+    let template = cube::face_corners(face);
+    let (u_axis, v_axis) = cube::face_tex_axes(face);
+
+    // corner of the full block face where the texture starts
+    let tex_origin = template
+        .iter()
+        .find(|v| v.tex_coords == [0.0, 0.0])
+        .expect("face template is missing a corner")
+        .position
+        + Vec3::from([0.5, 0.5, 0.5]);
+
+    template.map(|mut v| {
+        let corner = v.position + Vec3::from([0.5, 0.5, 0.5]);
+        let position: [f32; 3] =
+            std::array::from_fn(|i| from[i] + (to[i] - from[i]) * corner.0[i]);
+
+        v.tex_coords = match uv {
+            Some([u1, v1, u2, v2]) => [
+                (u1 + (u2 - u1) * v.tex_coords[0]) / 16.0,
+                (v1 + (v2 - v1) * v.tex_coords[1]) / 16.0,
+            ],
+            None => {
+                let offset: [f32; 3] = std::array::from_fn(|i| position[i] - tex_origin.0[i]);
+                let dot = |axis: [i32; 3]| (0..3).map(|i| offset[i] * axis[i] as f32).sum::<f32>();
+                [dot(u_axis), dot(v_axis)]
+            }
+        };
+        v.position = Vec3::from(position);
+        v.atlas_rect = atlas.rect();
+        v
+    })
+}
+
+/// Two diagonal quads through the block, each added once per side (in reverse corner order)
+/// so back-face culling doesn't hide them
+fn cross_quads(atlas: AtlasUV) -> Vec<BakedQuad> {
+    let diagonals = [
+        [[0.0, 0.0, 0.0], [1.0, 0.0, 1.0], [1.0, 1.0, 1.0], [0.0, 1.0, 0.0]],
+        [[0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 1.0]],
+    ];
+    let tex_coords = [[0.0, 1.0], [1.0, 1.0], [1.0, 0.0], [0.0, 0.0]];
+
+    let mut quads = Vec::new();
+    for corners in diagonals {
+        for order in [[0, 1, 2, 3], [0, 3, 2, 1]] {
+            let vertices = order.map(|i| BlockVertex {
+                position: Vec3::from(corners[i]),
+                tex_coords: tex_coords[i],
+                atlas_rect: atlas.rect(),
+            });
+            quads.push(BakedQuad {
+                vertices,
+                cullface: None,
+            });
+        }
+    }
+    quads
+}
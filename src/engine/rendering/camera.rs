@@ -3,9 +3,9 @@ use std::cmp::min;
 use crate::engine::core::input::{InputState, KeyBind};
 use glam::{Quat, Vec3};
 
-/// Currently this handles movement. Later the movement code will be exclusive to the player or entity controller.
 /// The camera will be used for all scenes later, so code can remain simple. May need to make multiple camera types later.
-/// This is just a simple fly camera for debugging purposes.
+/// Scenes with a player only use it for looking around and put it at the player's eyes, `fly` is
+/// the noclip mode for debugging.
 
 const MAX_PITCH_DEG: f32 = 89.99;
 
@@ -70,32 +70,40 @@ impl Camera {
         }
     }
 
+    /// Free fly movement and mouse look
     pub fn update(&mut self, delta_time: f32, input: &InputState) {
-        // Movement
+        self.fly(delta_time, input);
+        self.look(delta_time, input);
+    }
+
+    /// Moves along the view direction, going through everything
+    pub fn fly(&mut self, delta_time: f32, input: &InputState) {
         let forward = self.rotation_quat * Vec3::new(0.0, 0.0, 1.0);
         let right = self.rotation_quat * Vec3::new(1.0, 0.0, 0.0);
         let up = Vec3::new(0.0, 1.0, 0.0);
 
-        if input.get_keybind_is_pressed(KeyBind::new("DbgForward")) {
+        if input.get_keybind_is_pressed(KeyBind::new("MoveForward")) {
             self.position += forward * self.speed * delta_time;
         }
-        if input.get_keybind_is_pressed(KeyBind::new("DbgBackward")) {
+        if input.get_keybind_is_pressed(KeyBind::new("MoveBackward")) {
             self.position -= forward * self.speed * delta_time;
         }
-        if input.get_keybind_is_pressed(KeyBind::new("DbgLeft")) {
+        if input.get_keybind_is_pressed(KeyBind::new("MoveLeft")) {
             self.position -= right * self.speed * delta_time;
         }
-        if input.get_keybind_is_pressed(KeyBind::new("DbgRight")) {
+        if input.get_keybind_is_pressed(KeyBind::new("MoveRight")) {
             self.position += right * self.speed * delta_time;
         }
-        if input.get_keybind_is_pressed(KeyBind::new("DbgUp")) {
+        if input.get_keybind_is_pressed(KeyBind::new("MoveUp")) {
             self.position += up * self.speed * delta_time;
         }
-        if input.get_keybind_is_pressed(KeyBind::new("DbgDown")) {
+        if input.get_keybind_is_pressed(KeyBind::new("MoveDown")) {
             self.position -= up * self.speed * delta_time;
         }
+    }
 
-        // Mouse rotation
+    /// Rotates with the mouse
+    pub fn look(&mut self, delta_time: f32, input: &InputState) {
         let new_yaw = self.yaw() + (input.mouse_delta_x * self.sensitivity * delta_time);
         let new_pitch = (self.pitch() + (input.mouse_delta_y * self.sensitivity * delta_time))
             .clamp(-MAX_PITCH_DEG, MAX_PITCH_DEG);
@@ -1,4 +1,5 @@
-use crate::engine::rendering::vertex::BlockVertex;
+use crate::content::block::block::BlockFace;
+use crate::engine::rendering::vertex::{BlockVertex, FULL_ATLAS_RECT};
 use crate::utils::math::Vec3;
 use std::sync::Arc;
 use vulkano::buffer::{Buffer, BufferCreateInfo, BufferUsage, Subbuffer};
@@ -10,146 +11,182 @@ pub const CUBE_VERTICES: [BlockVertex; 36] = [
     BlockVertex {
         position: Vec3([-0.5, -0.5, 0.5]),
         tex_coords: [1.0, 1.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([-0.5, 0.5, 0.5]),
         tex_coords: [1.0, 0.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([0.5, 0.5, 0.5]),
         tex_coords: [0.0, 0.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([0.5, 0.5, 0.5]),
         tex_coords: [0.0, 0.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([0.5, -0.5, 0.5]),
         tex_coords: [0.0, 1.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([-0.5, -0.5, 0.5]),
         tex_coords: [1.0, 1.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([-0.5, -0.5, -0.5]),
         tex_coords: [0.0, 1.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([0.5, -0.5, -0.5]),
         tex_coords: [1.0, 1.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([0.5, 0.5, -0.5]),
         tex_coords: [1.0, 0.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([0.5, 0.5, -0.5]),
         tex_coords: [1.0, 0.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([-0.5, 0.5, -0.5]),
         tex_coords: [0.0, 0.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([-0.5, -0.5, -0.5]),
         tex_coords: [0.0, 1.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([-0.5, -0.5, -0.5]),
         tex_coords: [0.0, 1.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([-0.5, 0.5, -0.5]),
         tex_coords: [0.0, 0.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([-0.5, 0.5, 0.5]),
         tex_coords: [1.0, 0.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([-0.5, 0.5, 0.5]),
         tex_coords: [1.0, 0.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([-0.5, -0.5, 0.5]),
         tex_coords: [1.0, 1.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([-0.5, -0.5, -0.5]),
         tex_coords: [0.0, 1.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([0.5, -0.5, -0.5]),
         tex_coords: [1.0, 1.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([0.5, -0.5, 0.5]),
         tex_coords: [0.0, 1.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([0.5, 0.5, 0.5]),
         tex_coords: [0.0, 0.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([0.5, 0.5, 0.5]),
         tex_coords: [0.0, 0.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([0.5, 0.5, -0.5]),
         tex_coords: [1.0, 0.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([0.5, -0.5, -0.5]),
         tex_coords: [1.0, 1.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([-0.5, -0.5, 0.5]),
         tex_coords: [1.0, 1.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([0.5, -0.5, 0.5]),
         tex_coords: [0.0, 1.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([0.5, -0.5, -0.5]),
         tex_coords: [0.0, 0.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([0.5, -0.5, -0.5]),
         tex_coords: [0.0, 0.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([-0.5, -0.5, -0.5]),
         tex_coords: [1.0, 0.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([-0.5, -0.5, 0.5]),
         tex_coords: [1.0, 1.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([-0.5, 0.5, -0.5]),
         tex_coords: [1.0, 1.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([0.5, 0.5, -0.5]),
         tex_coords: [0.0, 1.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([0.5, 0.5, 0.5]),
         tex_coords: [0.0, 0.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([0.5, 0.5, 0.5]),
         tex_coords: [0.0, 0.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([-0.5, 0.5, 0.5]),
         tex_coords: [1.0, 0.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([-0.5, 0.5, -0.5]),
         tex_coords: [1.0, 1.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
 ];
 
@@ -157,26 +194,32 @@ pub const FRONT_FACE: [BlockVertex; 6] = [
     BlockVertex {
         position: Vec3([-0.5, -0.5, 0.5]),
         tex_coords: [1.0, 1.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([-0.5, 0.5, 0.5]),
         tex_coords: [1.0, 0.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([0.5, 0.5, 0.5]),
         tex_coords: [0.0, 0.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([0.5, 0.5, 0.5]),
         tex_coords: [0.0, 0.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([0.5, -0.5, 0.5]),
         tex_coords: [0.0, 1.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([-0.5, -0.5, 0.5]),
         tex_coords: [1.0, 1.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
 ];
 
@@ -184,130 +227,160 @@ pub const REAR_FACE: [BlockVertex; 6] = [
     BlockVertex {
         position: Vec3([-0.5, -0.5, -0.5]),
         tex_coords: [0.0, 1.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([0.5, -0.5, -0.5]),
         tex_coords: [1.0, 1.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([0.5, 0.5, -0.5]),
         tex_coords: [1.0, 0.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([0.5, 0.5, -0.5]),
         tex_coords: [1.0, 0.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([-0.5, 0.5, -0.5]),
         tex_coords: [0.0, 0.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([-0.5, -0.5, -0.5]),
         tex_coords: [0.0, 1.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
 ];
 pub const RIGHT_FACE: [BlockVertex; 6] = [
     BlockVertex {
         position: Vec3([-0.5, -0.5, -0.5]),
         tex_coords: [0.0, 1.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([-0.5, 0.5, -0.5]),
         tex_coords: [0.0, 0.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([-0.5, 0.5, 0.5]),
         tex_coords: [1.0, 0.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([-0.5, 0.5, 0.5]),
         tex_coords: [1.0, 0.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([-0.5, -0.5, 0.5]),
         tex_coords: [1.0, 1.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([-0.5, -0.5, -0.5]),
         tex_coords: [0.0, 1.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
 ];
 pub const LEFT_FACE: [BlockVertex; 6] = [
     BlockVertex {
         position: Vec3([0.5, -0.5, -0.5]),
         tex_coords: [1.0, 1.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([0.5, -0.5, 0.5]),
         tex_coords: [0.0, 1.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([0.5, 0.5, 0.5]),
         tex_coords: [0.0, 0.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([0.5, 0.5, 0.5]),
         tex_coords: [0.0, 0.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([0.5, 0.5, -0.5]),
         tex_coords: [1.0, 0.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([0.5, -0.5, -0.5]),
         tex_coords: [1.0, 1.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
 ];
 pub const BOTTOM_FACE: [BlockVertex; 6] = [
     BlockVertex {
         position: Vec3([-0.5, -0.5, 0.5]),
         tex_coords: [1.0, 1.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([0.5, -0.5, 0.5]),
         tex_coords: [0.0, 1.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([0.5, -0.5, -0.5]),
         tex_coords: [0.0, 0.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([0.5, -0.5, -0.5]),
         tex_coords: [0.0, 0.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([-0.5, -0.5, -0.5]),
         tex_coords: [1.0, 0.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([-0.5, -0.5, 0.5]),
         tex_coords: [1.0, 1.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
 ];
 pub const TOP_FACE: [BlockVertex; 6] = [
     BlockVertex {
         position: Vec3([-0.5, 0.5, -0.5]),
         tex_coords: [1.0, 1.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([0.5, 0.5, -0.5]),
         tex_coords: [0.0, 1.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([0.5, 0.5, 0.5]),
         tex_coords: [0.0, 0.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([0.5, 0.5, 0.5]),
         tex_coords: [0.0, 0.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([-0.5, 0.5, 0.5]),
         tex_coords: [1.0, 0.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
     BlockVertex {
         position: Vec3([-0.5, 0.5, -0.5]),
         tex_coords: [1.0, 1.0],
+        atlas_rect: FULL_ATLAS_RECT,
     },
 ];
 
@@ -385,3 +458,69 @@ pub fn create_cube_vertex_buffer(
     )
     .unwrap()
 }
+
+/// The face template for one side of a block
+pub fn face_template(face: BlockFace) -> [BlockVertex; 6] {
+    match face {
+        BlockFace::Top => TOP_FACE,
+        BlockFace::Bottom => BOTTOM_FACE,
+        BlockFace::North => REAR_FACE,
+        BlockFace::South => FRONT_FACE,
+        BlockFace::West => RIGHT_FACE,
+        BlockFace::East => LEFT_FACE,
+    }
+}
+
+/// Template vertices that are the four distinct corners of a face, going around the quad.
+/// Every face template is the triangles (0, 1, 2) and (2, 3, 0) of these corners.
+pub const QUAD_CORNERS: [usize; 4] = [0, 1, 2, 4];
+
+/// The four corners of a face template, in order around the quad
+pub fn face_corners(face: BlockFace) -> [BlockVertex; 4] {
+    let template = face_template(face);
+    QUAD_CORNERS.map(|i| template[i])
+}
+
+/// Directions the u and v texture coordinates increase in across a face template
+pub fn face_tex_axes(face: BlockFace) -> ([i32; 3], [i32; 3]) {
+    let template = face_template(face);
+    let corner = |uv: [f32; 2]| {
+        template
+            .iter()
+            .find(|v| v.tex_coords == uv)
+            .expect("face template is missing a corner")
+            .position
+    };
+
+    let origin = corner([0.0, 0.0]);
+    let axis = |p: Vec3| [0, 1, 2].map(|i| (p.0[i] - origin.0[i]).round() as i32);
+    (axis(corner([1.0, 0.0])), axis(corner([0.0, 1.0])))
+}
+
+/// Corner indices (bit 0 = x, bit 1 = y, bit 2 = z) of the twelve edges of a box
+const BOX_EDGES: [[usize; 2]; 12] = [
+    [0, 1], [2, 3], [4, 5], [6, 7], // along x
+    [0, 2], [1, 3], [4, 6], [5, 7], // along y
+    [0, 4], [1, 5], [2, 6], [3, 7], // along z
+];
+
+/// Line list vertices outlining the box from `min` to `max`, for use with a line topology pipeline
+pub fn box_outline_vertices(min: [f32; 3], max: [f32; 3]) -> [BlockVertex; 24] {
+    let corner = |i: usize| {
+        BlockVertex::new(
+            [
+                if i & 1 == 0 { min[0] } else { max[0] },
+                if i & 2 == 0 { min[1] } else { max[1] },
+                if i & 4 == 0 { min[2] } else { max[2] },
+            ],
+            [0.0, 0.0],
+        )
+    };
+
+    let mut vertices = [BlockVertex::default(); 24];
+    for (i, edge) in BOX_EDGES.iter().enumerate() {
+        vertices[i * 2] = corner(edge[0]);
+        vertices[i * 2 + 1] = corner(edge[1]);
+    }
+    vertices
+}
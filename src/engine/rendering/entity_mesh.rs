@@ -0,0 +1,85 @@
+use crate::content::block::block::BlockFace;
+use crate::engine::rendering::baked_model::{BakedGeometry, BakedModel};
+use crate::engine::rendering::cube;
+use crate::engine::rendering::vertex::BlockVertex;
+use crate::utils::math::Vec3;
+use rapidhash::RapidHashMap;
+use std::sync::Arc;
+use vulkano::buffer::{Buffer, BufferCreateInfo, BufferUsage, Subbuffer};
+use vulkano::memory::allocator::{AllocationCreateInfo, MemoryTypeFilter, StandardMemoryAllocator};
+
+/// Vertex buffers of the models entities are drawn with, built the first time an entity needs
+/// one and kept for as long as the scene lives
+pub struct EntityMeshes {
+    allocator: Arc<StandardMemoryAllocator>,
+    /// `None` for models without anything to draw, so they aren't built again every frame
+    meshes: RapidHashMap<String, Option<Subbuffer<[BlockVertex]>>>,
+}
+
+impl EntityMeshes {
+    pub fn new(allocator: Arc<StandardMemoryAllocator>) -> Self {
+        Self {
+            allocator,
+            meshes: RapidHashMap::default(),
+        }
+    }
+
+    /// Builds the mesh of `model`, a key of `block_models`, if it isn't built yet.
+    /// Unknown models get the missing model.
+    pub fn prepare(&mut self, model: &str, block_models: &RapidHashMap<String, BakedModel>) {
+        if self.meshes.contains_key(model) {
+            return;
+        }
+        let vertices = model_vertices(block_models.get(model).unwrap_or(&BakedModel::MISSING));
+        let mesh = (!vertices.is_empty()).then(|| {
+            Buffer::from_iter(
+                self.allocator.clone(),
+                BufferCreateInfo {
+                    usage: BufferUsage::VERTEX_BUFFER,
+                    ..Default::default()
+                },
+                AllocationCreateInfo {
+                    memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
+                        | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+                    ..Default::default()
+                },
+                vertices,
+            )
+            .unwrap()
+        });
+        self.meshes.insert(model.to_string(), mesh);
+    }
+
+    /// The mesh of `model`, if it's been prepared and has anything to draw
+    pub fn get(&self, model: &str) -> Option<&Subbuffer<[BlockVertex]>> {
+        self.meshes.get(model)?.as_ref()
+    }
+}
+
+/// Triangle list of every face of a baked model. The origin is moved to the centre of the bottom
+/// of the block, the same point a `Transform` puts at its position.
+fn model_vertices(model: &BakedModel) -> Vec<BlockVertex> {
+    match &model.geometry {
+        BakedGeometry::Cube(face_uvs) => BlockFace::ALL
+            .into_iter()
+            .flat_map(|face| {
+                let face_uv = face_uvs.face(face);
+                // the face templates are centred on the block
+                cube::face_template(face).map(|v| BlockVertex {
+                    position: v.position + Vec3::from([0.0, 0.5, 0.0]),
+                    tex_coords: face_uv.transform(v.tex_coords),
+                    atlas_rect: face_uv.atlas.rect(),
+                })
+            })
+            .collect(),
+        BakedGeometry::Quads(quads) => quads
+            .iter()
+            .flat_map(|quad| {
+                [0, 1, 2, 2, 3, 0].map(|corner| BlockVertex {
+                    position: quad.vertices[corner].position + Vec3::from([-0.5, 0.0, -0.5]),
+                    ..quad.vertices[corner]
+                })
+            })
+            .collect(),
+    }
+}
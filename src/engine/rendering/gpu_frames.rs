@@ -0,0 +1,34 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Frames submitted to the GPU and how many of those it has finished, written by the app from
+/// each frame's fence. Anything the commands of a frame read can be written again once
+/// `completed` has caught up with what `submitted` was when it was last drawn.
+pub struct GpuFrames {
+    submitted: AtomicU64,
+    completed: AtomicU64,
+}
+
+pub static GPU_FRAMES: GpuFrames = GpuFrames {
+    submitted: AtomicU64::new(0),
+    completed: AtomicU64::new(0),
+};
+
+impl GpuFrames {
+    /// Counts a frame as submitted, returns its number
+    pub fn submit(&self) -> u64 {
+        self.submitted.fetch_add(1, Ordering::AcqRel) + 1
+    }
+
+    /// Marks every frame up to `frame` as finished on the GPU
+    pub fn complete(&self, frame: u64) {
+        self.completed.fetch_max(frame, Ordering::Release);
+    }
+
+    pub fn submitted(&self) -> u64 {
+        self.submitted.load(Ordering::Acquire)
+    }
+
+    pub fn completed(&self) -> u64 {
+        self.completed.load(Ordering::Acquire)
+    }
+}
@@ -0,0 +1,301 @@
+use crate::engine::rendering::gpu_frames::GPU_FRAMES;
+use crate::engine::rendering::vertex::ChunkVertex;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Arc;
+use vulkano::buffer::{Buffer, BufferCreateInfo, BufferUsage, Subbuffer};
+use vulkano::memory::MemoryPropertyFlags;
+use vulkano::memory::allocator::{AllocationCreateInfo, MemoryTypeFilter, StandardMemoryAllocator};
+
+/// Vertices in each page of the arena (24 bytes each, so 12 MiB per page)
+const PAGE_VERTICES: u64 = 1 << 19;
+
+/// First fit allocator over `capacity` slots. Freed ranges are merged with the free ranges
+/// right next to them, so the free list only splits up as far as the allocations do.
+pub struct FreeList {
+    free: BTreeMap<u64, u64>, // offset -> length of each free range
+    capacity: u64,
+}
+
+impl FreeList {
+    pub fn new(capacity: u64) -> Self {
+        let mut free = BTreeMap::new();
+        if capacity > 0 {
+            free.insert(0, capacity);
+        }
+        Self { free, capacity }
+    }
+
+    /// Takes `len` slots from the first free range big enough, returns their offset
+    pub fn allocate(&mut self, len: u64) -> Option<u64> {
+        let (&offset, &free_len) = self.free.iter().find(|&(_, &free_len)| free_len >= len)?;
+        self.free.remove(&offset);
+        if free_len > len {
+            self.free.insert(offset + len, free_len - len);
+        }
+        Some(offset)
+    }
+
+    /// Gives back slots taken by `allocate`
+    pub fn free(&mut self, offset: u64, len: u64) {
+        debug_assert!(offset + len <= self.capacity);
+        let mut start = offset;
+        let mut end = offset + len;
+
+        if let Some((&prev, &prev_len)) = self.free.range(..offset).next_back()
+            && prev + prev_len == offset
+        {
+            self.free.remove(&prev);
+            start = prev;
+        }
+        if let Some(next_len) = self.free.remove(&end) {
+            end += next_len;
+        }
+        self.free.insert(start, end - start);
+    }
+
+    pub fn capacity(&self) -> u64 {
+        self.capacity
+    }
+
+    pub fn free_slots(&self) -> u64 {
+        self.free.values().sum()
+    }
+
+    pub fn free_ranges(&self) -> usize {
+        self.free.len()
+    }
+
+    pub fn largest_free(&self) -> u64 {
+        self.free.values().copied().max().unwrap_or(0)
+    }
+}
+
+/// Usage of the mesh arena, in vertices
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ArenaStats {
+    pub capacity: u64,
+    pub used: u64, // includes freed ranges that are still waiting to be reused
+    pub free_ranges: usize,
+    pub largest_free: u64,
+}
+
+impl ArenaStats {
+    /// Share of the arena holding meshes, 0 to 1
+    pub fn utilization(&self) -> f32 {
+        if self.capacity == 0 {
+            return 0.0;
+        }
+        self.used as f32 / self.capacity as f32
+    }
+
+    /// Share of the free space outside the largest free range, 0 to 1.
+    /// 0 means all free space is in one piece.
+    pub fn fragmentation(&self) -> f32 {
+        let free = self.capacity - self.used;
+        if free == 0 {
+            return 0.0;
+        }
+        1.0 - self.largest_free as f32 / free as f32
+    }
+}
+
+/// A mesh's vertices in the arena. Has to be given back with `MeshArena::free`.
+pub struct ArenaAllocation {
+    page: usize,
+    offset: u64,
+    vertices: Subbuffer<[ChunkVertex]>,
+}
+
+impl ArenaAllocation {
+    pub fn vertices(&self) -> &Subbuffer<[ChunkVertex]> {
+        &self.vertices
+    }
+
+    /// Page the vertices are in, see `MeshArena::page_buffer`
+    pub fn page(&self) -> usize {
+        self.page
+    }
+
+    /// Index of the first vertex in its page
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+}
+
+struct ArenaPage {
+    buffer: Subbuffer<[ChunkVertex]>,
+    free_list: FreeList,
+}
+
+/// Vertex memory for all chunk meshes. Meshes are copied into ranges of a few large buffers
+/// (pages) instead of each getting their own buffer, and remeshing reuses the freed ranges.
+/// A new page is only added when no page has a free range big enough.
+pub struct MeshArena {
+    allocator: Arc<StandardMemoryAllocator>,
+    pages: Vec<ArenaPage>,
+    retired: VecDeque<(u64, usize, u64, u64)>, // frames submitted when freed, page, offset, length
+}
+
+impl MeshArena {
+    pub fn new(allocator: Arc<StandardMemoryAllocator>) -> Self {
+        Self {
+            allocator,
+            pages: Vec::new(),
+            retired: VecDeque::new(),
+        }
+    }
+
+    /// Copies a mesh's vertices into the arena
+    pub fn upload(&mut self, vertices: &[ChunkVertex]) -> ArenaAllocation {
+        let len = vertices.len() as u64;
+        let (page, offset) = self.allocate(len);
+
+        let slice = self.pages[page].buffer.clone().slice(offset..offset + len);
+        let mapped = slice.mapped_slice().expect("mesh arena pages are host mapped");
+        // Frames in flight draw from the whole page, so the checked `write` would refuse while
+        // any of them is running. Nothing reads this range though: it's either new or was only
+        // released after the GPU finished every frame that could draw from it, see
+        // `release_finished`. Pages are host coherent, so the copy needs no flush.
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                vertices.as_ptr(),
+                mapped.cast::<ChunkVertex>().as_ptr(),
+                vertices.len(),
+            );
+        }
+
+        ArenaAllocation {
+            page,
+            offset,
+            vertices: slice,
+        }
+    }
+
+    /// Gives a mesh's range back. The mesh must not be drawn anymore, but frames already
+    /// submitted may still read it, so the range is only reused once they finished.
+    pub fn free(&mut self, allocation: ArenaAllocation) {
+        let len = allocation.vertices.len();
+        let submitted = GPU_FRAMES.submitted();
+        self.retired.push_back((submitted, allocation.page, allocation.offset, len));
+    }
+
+    /// Releases freed ranges whose frames the GPU has finished, going by the frame fences
+    /// counted in `GPU_FRAMES`. Call once per frame.
+    pub fn release_finished(&mut self) {
+        let completed = GPU_FRAMES.completed();
+        while let Some(&(submitted, page, offset, len)) = self.retired.front() {
+            if submitted > completed {
+                break;
+            }
+            self.retired.pop_front();
+            self.pages[page].free_list.free(offset, len);
+        }
+    }
+
+    /// Whole vertex buffer of a page, for drawing all meshes in it with one bind
+    pub fn page_buffer(&self, page: usize) -> &Subbuffer<[ChunkVertex]> {
+        &self.pages[page].buffer
+    }
+
+    pub fn stats(&self) -> ArenaStats {
+        let mut stats = ArenaStats::default();
+        for page in &self.pages {
+            stats.capacity += page.free_list.capacity();
+            stats.used += page.free_list.capacity() - page.free_list.free_slots();
+            stats.free_ranges += page.free_list.free_ranges();
+            stats.largest_free = stats.largest_free.max(page.free_list.largest_free());
+        }
+        stats
+    }
+
+    fn allocate(&mut self, len: u64) -> (usize, u64) {
+        for (i, page) in self.pages.iter_mut().enumerate() {
+            if let Some(offset) = page.free_list.allocate(len) {
+                return (i, offset);
+            }
+        }
+
+        // meshes bigger than a page get a page of their own size
+        let capacity = len.max(PAGE_VERTICES);
+        // coherent so `upload` can copy straight into the mapping, see there
+        let mut memory_type_filter =
+            MemoryTypeFilter::PREFER_DEVICE | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE;
+        memory_type_filter.required_flags |= MemoryPropertyFlags::HOST_COHERENT;
+        let buffer = Buffer::new_slice(
+            self.allocator.clone(),
+            BufferCreateInfo {
+                usage: BufferUsage::VERTEX_BUFFER,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter,
+                ..Default::default()
+            },
+            capacity,
+        )
+        .unwrap();
+
+        let mut free_list = FreeList::new(capacity);
+        let offset = free_list.allocate(len).unwrap();
+        self.pages.push(ArenaPage { buffer, free_list });
+        (self.pages.len() - 1, offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allocations_take_the_first_range_that_fits() {
+        let mut list = FreeList::new(100);
+        assert_eq!(list.allocate(10), Some(0));
+        assert_eq!(list.allocate(20), Some(10));
+        assert_eq!(list.allocate(30), Some(30));
+
+        // a 10 slot hole in front, too small for 15 so that goes after the last allocation
+        list.free(0, 10);
+        assert_eq!(list.allocate(15), Some(60));
+
+        // the hole grows to 30 and takes both of the next allocations
+        list.free(10, 20);
+        assert_eq!(list.allocate(5), Some(0));
+        assert_eq!(list.allocate(12), Some(5));
+
+        assert_eq!(list.allocate(100), None);
+        assert_eq!(list.free_slots(), 100 - 5 - 12 - 30 - 15);
+    }
+
+    #[test]
+    fn freed_ranges_merge_with_their_neighbors() {
+        let mut list = FreeList::new(40);
+        let offsets: Vec<u64> = (0..4).map(|_| list.allocate(10).unwrap()).collect();
+        assert_eq!(list.free_ranges(), 0);
+
+        list.free(offsets[0], 10);
+        list.free(offsets[2], 10);
+        assert_eq!(list.free_ranges(), 2);
+        assert_eq!(list.largest_free(), 10);
+
+        // merges with the range before it and the one after it
+        list.free(offsets[1], 10);
+        assert_eq!(list.free_ranges(), 1);
+        assert_eq!(list.largest_free(), 30);
+
+        list.free(offsets[3], 10);
+        assert_eq!(list.free_ranges(), 1);
+        assert_eq!(list.largest_free(), 40);
+        assert_eq!(list.allocate(40), Some(0));
+    }
+
+    #[test]
+    fn exact_fits_leave_no_empty_ranges() {
+        let mut list = FreeList::new(16);
+        assert_eq!(list.allocate(16), Some(0));
+        assert_eq!(list.free_ranges(), 0);
+        assert_eq!(list.largest_free(), 0);
+        assert_eq!(list.allocate(1), None);
+
+        assert_eq!(FreeList::new(0).allocate(1), None);
+    }
+}
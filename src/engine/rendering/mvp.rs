@@ -41,7 +41,17 @@ impl Default for MVP {
     }
 }
 
+/// Same flip of y and z the vertex shaders apply between the projection and view matrices
+pub const UPSIDE_DOWN: Mat4 = Mat4::from_cols_array(&[
+    1.0, 0.0, 0.0, 0.0, 0.0, -1.0, 0.0, 0.0, 0.0, 0.0, -1.0, 0.0, 0.0, 0.0, 0.0, 1.0,
+]);
+
 impl MVP {
+    /// World to clip space transform, matching what the vertex shaders compute
+    pub fn view_projection(&self) -> Mat4 {
+        Mat4::from(self.proj) * UPSIDE_DOWN * Mat4::from(self.view)
+    }
+
     pub fn apply_camera_transforms(&self, camera: Camera) -> Self {
         let translation = Mat4::from_translation(-camera.position);
         let rotation = Mat4::from_quat(camera.rotation_quat.conjugate());
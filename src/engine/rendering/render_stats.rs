@@ -0,0 +1,81 @@
+use crate::engine::rendering::mesh_arena::ArenaStats;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+/// Counts from the last recorded frame, written by scenes while drawing and read by the debug UI
+pub struct RenderStats {
+    chunks_drawn: AtomicUsize,
+    chunks_culled: AtomicUsize,
+    chunk_draw_calls: AtomicUsize,
+    entities_drawn: AtomicUsize,
+    entity_draw_calls: AtomicUsize,
+    arena_capacity: AtomicU64,
+    arena_used: AtomicU64,
+    arena_free_ranges: AtomicUsize,
+    arena_largest_free: AtomicU64,
+}
+
+pub static RENDER_STATS: RenderStats = RenderStats {
+    chunks_drawn: AtomicUsize::new(0),
+    chunks_culled: AtomicUsize::new(0),
+    chunk_draw_calls: AtomicUsize::new(0),
+    entities_drawn: AtomicUsize::new(0),
+    entity_draw_calls: AtomicUsize::new(0),
+    arena_capacity: AtomicU64::new(0),
+    arena_used: AtomicU64::new(0),
+    arena_free_ranges: AtomicUsize::new(0),
+    arena_largest_free: AtomicU64::new(0),
+};
+
+impl RenderStats {
+    pub fn set_chunk_counts(&self, drawn: usize, culled: usize) {
+        self.chunks_drawn.store(drawn, Ordering::Relaxed);
+        self.chunks_culled.store(culled, Ordering::Relaxed);
+    }
+
+    pub fn chunks_drawn(&self) -> usize {
+        self.chunks_drawn.load(Ordering::Relaxed)
+    }
+
+    pub fn chunks_culled(&self) -> usize {
+        self.chunks_culled.load(Ordering::Relaxed)
+    }
+
+    /// Indirect draws the chunks took, one per render layer and arena page with visible faces
+    pub fn set_chunk_draw_calls(&self, draw_calls: usize) {
+        self.chunk_draw_calls.store(draw_calls, Ordering::Relaxed);
+    }
+
+    pub fn chunk_draw_calls(&self) -> usize {
+        self.chunk_draw_calls.load(Ordering::Relaxed)
+    }
+
+    /// Entities drawn and the instanced draws they took, one per model
+    pub fn set_entity_counts(&self, drawn: usize, draw_calls: usize) {
+        self.entities_drawn.store(drawn, Ordering::Relaxed);
+        self.entity_draw_calls.store(draw_calls, Ordering::Relaxed);
+    }
+
+    pub fn entities_drawn(&self) -> usize {
+        self.entities_drawn.load(Ordering::Relaxed)
+    }
+
+    pub fn entity_draw_calls(&self) -> usize {
+        self.entity_draw_calls.load(Ordering::Relaxed)
+    }
+
+    pub fn set_arena_stats(&self, stats: ArenaStats) {
+        self.arena_capacity.store(stats.capacity, Ordering::Relaxed);
+        self.arena_used.store(stats.used, Ordering::Relaxed);
+        self.arena_free_ranges.store(stats.free_ranges, Ordering::Relaxed);
+        self.arena_largest_free.store(stats.largest_free, Ordering::Relaxed);
+    }
+
+    pub fn arena_stats(&self) -> ArenaStats {
+        ArenaStats {
+            capacity: self.arena_capacity.load(Ordering::Relaxed),
+            used: self.arena_used.load(Ordering::Relaxed),
+            free_ranges: self.arena_free_ranges.load(Ordering::Relaxed),
+            largest_free: self.arena_largest_free.load(Ordering::Relaxed),
+        }
+    }
+}
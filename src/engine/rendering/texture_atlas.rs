@@ -1,8 +1,11 @@
-use crate::content::block::block::{Block, BlockModel};
+use crate::content::block::block::{Block, BlockFace, BlockModel, BlockState};
+use crate::content::block::block_state::state_key;
+use crate::engine::rendering::baked_model::BakedModel;
+use crate::engine::rendering::cube;
 use crate::engine::core::content_loader::GameContent;
-use image::{GenericImage, GenericImageView, RgbaImage};
+use image::{Rgba, RgbaImage};
 use rapidhash::RapidHashMap;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::sync::Arc;
 use vulkano::device::{DeviceOwned, Queue};
 use vulkano::format::Format;
@@ -11,22 +14,119 @@ use vulkano::image::view::ImageView;
 use vulkano::image::{Image, ImageCreateInfo, ImageType, ImageUsage};
 use vulkano::memory::allocator::{AllocationCreateInfo, MemoryTypeFilter, StandardMemoryAllocator};
 
-/// UV mapping for one block in the atlas
-#[derive(Debug, Clone, Copy)]
+/// UV mapping for one texture in the atlas
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct AtlasUV {
     pub uv_min: [f32; 2],
     pub uv_max: [f32; 2],
 }
 
-/// Contains the generated atlas and lookup table
+impl AtlasUV {
+    /// The entry as `min.xy, size.xy`, the layout `ChunkVertex::pack` stores in `atlas_rect`
+    pub fn rect(&self) -> [f32; 4] {
+        [
+            self.uv_min[0],
+            self.uv_min[1],
+            self.uv_max[0] - self.uv_min[0],
+            self.uv_max[1] - self.uv_min[1],
+        ]
+    }
+}
+
+/// Used for faces whose texture is missing, shows the whole atlas
+pub const MISSING_UV: AtlasUV = AtlasUV {
+    uv_min: [0.0, 0.0],
+    uv_max: [1.0, 1.0],
+};
+
+/// Texture of one block face: its atlas entry and how the face's tile coordinates map onto it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FaceUV {
+    pub atlas: AtlasUV,
+    /// `[u', v'] = [[a, b], [c, d]] * [u, v]`. Only quarter turns and flips, so rotated models
+    /// keep their textures lined up with the model instead of the world.
+    pub tex_transform: [[i8; 2]; 2],
+}
+
+impl FaceUV {
+    pub const IDENTITY_TRANSFORM: [[i8; 2]; 2] = [[1, 0], [0, 1]];
+
+    /// Applies `tex_transform` to tile coordinates
+    pub fn transform(&self, tex_coords: [f32; 2]) -> [f32; 2] {
+        let [[a, b], [c, d]] = self.tex_transform.map(|row| row.map(f32::from));
+        [
+            a * tex_coords[0] + b * tex_coords[1],
+            c * tex_coords[0] + d * tex_coords[1],
+        ]
+    }
+}
+
+/// Textures of each face of one block state, indexed by `BlockFace`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlockFaceUVs {
+    pub faces: [FaceUV; 6],
+}
+
+impl BlockFaceUVs {
+    pub const MISSING: BlockFaceUVs = BlockFaceUVs {
+        faces: [FaceUV {
+            atlas: MISSING_UV,
+            tex_transform: FaceUV::IDENTITY_TRANSFORM,
+        }; 6],
+    };
+
+    /// Resolves the textures of a (possibly rotated) block state
+    pub fn from_state(
+        model: &BlockModel,
+        state: &BlockState,
+        texture_uvs: &RapidHashMap<String, AtlasUV>,
+    ) -> Self {
+        let dot = |a: [i32; 3], b: [i32; 3]| (a[0] * b[0] + a[1] * b[1] + a[2] * b[2]) as i8;
+
+        let faces = BlockFace::ALL.map(|face| {
+            // the model face that points this way once the model is rotated
+            let model_face = BlockFace::ALL
+                .into_iter()
+                .find(|model_face| state.rotate(model_face.normal()) == face.normal())
+                .expect("block rotations are quarter turns");
+
+            let atlas = model
+                .face_texture(model_face)
+                .and_then(|tex| texture_uvs.get(tex))
+                .copied()
+                .unwrap_or(MISSING_UV);
+
+            // express the rotated model texture's axes in this face's tile coordinates
+            let (u, v) = cube::face_tex_axes(face);
+            let (model_u, model_v) = cube::face_tex_axes(model_face);
+            let (model_u, model_v) = (state.rotate(model_u), state.rotate(model_v));
+
+            FaceUV {
+                atlas,
+                tex_transform: [
+                    [dot(u, model_u), dot(v, model_u)],
+                    [dot(u, model_v), dot(v, model_v)],
+                ],
+            }
+        });
+
+        Self { faces }
+    }
+
+    pub fn face(&self, face: BlockFace) -> FaceUV {
+        self.faces[face as usize]
+    }
+}
+
+/// Contains the generated atlas and lookup tables
 pub struct TextureAtlas {
     pub image_view: Arc<ImageView>,
     pub sampler: Arc<Sampler>,
-    pub block_uvs: RapidHashMap<String, AtlasUV>,
+    /// Baked models keyed by block state key, built from each state's model and rotation
+    pub block_models: RapidHashMap<String, BakedModel>,
 }
 
 impl TextureAtlas {
-    // TODO: This will actually create a new texture in the atlas for every defined texture. Meaning duplicates can arise. Fix.
     /// Creates the texture atlas from all block textures
     pub fn generate(
         allocator: Arc<StandardMemoryAllocator>,
@@ -39,75 +139,82 @@ impl TextureAtlas {
         let blocks: Vec<&Block> = content.blocks.iter().map(|(_, b)| b.as_ref()).collect();
         assert!(!blocks.is_empty(), "No blocks found.");
 
-        // Collect images: use "all" texture if available, fallback to first available texture
-        let mut images: Vec<(String, RgbaImage)> = Vec::new();
+        // Collect every texture the models use. Sorted so the layout doesn't change between runs.
+        let models: Vec<&BlockModel> = blocks
+            .iter()
+            .flat_map(|block| {
+                let states = &block.block_states;
+                states.default.iter().chain(states.variants.values())
+            })
+            .filter_map(|state| state.model.as_ref())
+            .collect();
 
-        for block in &blocks {
-            if let Some(state) = &block.block_states.default {
-                if let Some(model) = &state.model {
-                    // Use texture key "all" first, otherwise the first texture
-                    let tex_entry = model
-                        .textures
-                        .get("all")
-                        .or_else(|| model.textures.values().next());
-
-                    if let Some(tex) = tex_entry {
-                        let path = format!("assets/{}", tex);
-                        match image::open(&path) {
-                            Ok(img) => images.push((block.id.clone(), img.to_rgba8())),
-                            Err(e) => println!("Failed to load texture for {}: {}", block.id, e),
-                        }
-                    }
-                }
+        let texture_paths: BTreeSet<&String> = models
+            .iter()
+            .flat_map(|model| {
+                let face_textures = BlockFace::ALL.map(|face| model.face_texture(face));
+                face_textures.into_iter().flatten().chain(model.textures.values())
+            })
+            .collect();
+
+        let mut images: Vec<(String, RgbaImage)> = Vec::new();
+        for tex in texture_paths {
+            let path = format!("assets/{}", tex);
+            match image::open(&path) {
+                Ok(img) => images.push((tex.clone(), img.to_rgba8())),
+                Err(e) => println!("Failed to load texture {}: {}", tex, e),
             }
         }
 
-        // Assume all block textures are the same size for now //TODO: Maybe change that.
         assert!(!images.is_empty(), "No block textures loaded.");
 
-        // Compute atlas dimensions
-        let tile_size = images[0].1.width();
-        let tiles_per_row = (images.len() as f32).sqrt().ceil() as u32;
-        let atlas_width = tile_size * tiles_per_row;
-        let atlas_height = tile_size * ((images.len() as u32 + tiles_per_row - 1) / tiles_per_row);
+        let AtlasImage {
+            mip_levels,
+            texture_uvs,
+        } = AtlasImage::pack(images);
+        let [atlas_width, atlas_height] = [mip_levels[0].width(), mip_levels[0].height()];
 
-        // Create empty atlas and fill it
-        let mut atlas = RgbaImage::new(atlas_width, atlas_height);
-
-        let mut block_uvs = RapidHashMap::default();
-
-        // Paste each block image into atlas
-        for (i, (id, img)) in images.iter().enumerate() {
-            let x = (i as u32 % tiles_per_row) * tile_size;
-            let y = (i as u32 / tiles_per_row) * tile_size;
-
-            atlas.copy_from(img, x, y).unwrap();
+        let mut block_models = RapidHashMap::default();
+        for block in &blocks {
+            for properties in block.all_properties() {
+                let Some(state) = block.state(&properties) else {
+                    continue;
+                };
+                let Some(model) = &state.model else {
+                    continue;
+                };
+                let baked = BakedModel::bake(model, state, &block.properties, &texture_uvs);
+                block_models.insert(state_key(&block.id, &properties), baked);
+            }
 
-            // Normalized UVs
-            let uv_min = [
-                x as f32 / atlas_width as f32,
-                y as f32 / atlas_height as f32,
-            ];
-            let uv_max = [
-                (x + tile_size) as f32 / atlas_width as f32,
-                (y + tile_size) as f32 / atlas_height as f32,
-            ];
-            block_uvs.insert(id.clone(), AtlasUV { uv_min, uv_max });
+            // a bare ID of a block with properties shows its default state
+            if let Some(default_model) = block_models.get(&block.default_state_key()) {
+                block_models.insert(block.id.clone(), default_model.clone());
+            }
         }
 
         // This saves a debug copy so I can make sure it works fine and see what's going on easily.
-        //atlas.save("atlas_debug.png").unwrap();
+        //mip_levels[0].save("atlas_debug.png").unwrap();
         //println!("Saved texture atlas to atlas_debug.png ({}x{})", atlas_width, atlas_height);
 
         // ---- Upload to GPU ----
-        let img_data = atlas.into_raw();
+        // all mip levels go into one staging buffer, one after the other
+        let mut img_data = Vec::new();
+        let mut level_offsets = Vec::with_capacity(mip_levels.len());
+        for level in &mip_levels {
+            level_offsets.push(img_data.len() as u64);
+            img_data.extend_from_slice(level.as_raw());
+        }
 
         use vulkano::buffer::{Buffer, BufferCreateInfo, BufferUsage};
         use vulkano::command_buffer::{
-            AutoCommandBufferBuilder, CommandBufferUsage, CopyBufferToImageInfo,
+            AutoCommandBufferBuilder, BufferImageCopy, CommandBufferUsage, CopyBufferToImageInfo,
             allocator::StandardCommandBufferAllocator,
         };
-        use vulkano::image::sampler::{Filter, Sampler, SamplerAddressMode, SamplerCreateInfo};
+        use vulkano::image::sampler::{
+            Filter, LOD_CLAMP_NONE, Sampler, SamplerAddressMode, SamplerCreateInfo,
+            SamplerMipmapMode,
+        };
         use vulkano::sync::{self, GpuFuture};
 
         let texture_image = Image::new(
@@ -116,6 +223,7 @@ impl TextureAtlas {
                 image_type: ImageType::Dim2d,
                 format: Format::R8G8B8A8_SRGB,
                 extent: [atlas_width, atlas_height, 1],
+                mip_levels: mip_levels.len() as u32,
                 usage: ImageUsage::TRANSFER_DST | ImageUsage::SAMPLED,
                 ..Default::default()
             },
@@ -154,12 +262,23 @@ impl TextureAtlas {
         )
         .unwrap();
 
-        builder
-            .copy_buffer_to_image(CopyBufferToImageInfo::buffer_image(
-                staging,
-                texture_image.clone(),
-            ))
-            .unwrap();
+        let mut copy_info = CopyBufferToImageInfo::buffer_image(staging, texture_image.clone());
+        copy_info.regions = mip_levels
+            .iter()
+            .zip(&level_offsets)
+            .enumerate()
+            .map(|(mip_level, (level, &buffer_offset))| {
+                let mut image_subresource = texture_image.subresource_layers();
+                image_subresource.mip_level = mip_level as u32;
+                BufferImageCopy {
+                    buffer_offset,
+                    image_subresource,
+                    image_extent: [level.width(), level.height(), 1],
+                    ..Default::default()
+                }
+            })
+            .collect();
+        builder.copy_buffer_to_image(copy_info).unwrap();
 
         let command_buffer = builder.build().unwrap();
 
@@ -179,6 +298,8 @@ impl TextureAtlas {
             SamplerCreateInfo {
                 mag_filter: Filter::Nearest, // // Basically the same as nearest neighbor. Keeps sharp pixels.
                 min_filter: Filter::Nearest,
+                mipmap_mode: SamplerMipmapMode::Linear, // blend between mip levels so distant terrain doesn't shimmer
+                lod: 0.0..=LOD_CLAMP_NONE,
                 address_mode: [
                     SamplerAddressMode::Repeat,
                     SamplerAddressMode::Repeat,
@@ -190,14 +311,392 @@ impl TextureAtlas {
         .unwrap();
 
         println!(
-            "Texture Atlas uploaded to GPU ({}x{} tiles), sampler created.",
-            atlas_width, atlas_height
+            "Texture Atlas uploaded to GPU ({}x{} pixels, {} mip levels), sampler created.",
+            atlas_width,
+            atlas_height,
+            mip_levels.len()
         );
 
         Self {
             image_view,
             sampler,
-            block_uvs,
+            block_models,
+        }
+    }
+}
+
+/// Empty pixels around every tile, filled by extending the tile's edges so filtering and the
+/// smaller mip levels don't pick up the neighboring tiles
+const GUTTER: u32 = 8;
+
+/// Each mip level halves the gutter, so stop while it still has at least one pixel left
+const MAX_MIP_LEVELS: u32 = GUTTER.ilog2() + 1;
+
+/// Tiles are placed on multiples of this, so a pixel of the smallest mip level never covers
+/// more than one tile
+const TILE_ALIGNMENT: u32 = 1 << (MAX_MIP_LEVELS - 1);
+
+/// Atlas pixels before they're uploaded to the GPU
+pub struct AtlasImage {
+    /// Level 0 first, every following level half the size of the previous one
+    pub mip_levels: Vec<RgbaImage>,
+    /// UVs of each texture's tile (without its gutter), keyed by texture path
+    pub texture_uvs: RapidHashMap<String, AtlasUV>,
+}
+
+impl AtlasImage {
+    /// Packs textures of any size onto shelves, tallest first. Textures are deduplicated by path
+    /// (the first one wins), and the atlas' sides are powers of two so every mip level lines up.
+    pub fn pack(textures: Vec<(String, RgbaImage)>) -> Self {
+        let textures: BTreeMap<String, RgbaImage> =
+            textures.into_iter().rev().collect(); // reversed so the first duplicate is kept
+
+        // size of each texture including its gutter
+        let cell_size = |img: &RgbaImage| {
+            [img.width(), img.height()].map(|side| (side + 2 * GUTTER).next_multiple_of(TILE_ALIGNMENT))
+        };
+
+        let mut order: Vec<(&String, &RgbaImage)> = textures.iter().collect();
+        order.sort_by_key(|(path, img)| (std::cmp::Reverse(cell_size(img)[1]), *path));
+
+        // start from a square that would fit everything without wasted space
+        let total_area: u32 = order.iter().map(|(_, img)| cell_size(img).iter().product::<u32>()).sum();
+        let widest = order.iter().map(|(_, img)| cell_size(img)[0]).max().unwrap_or(1);
+        let atlas_width = widest.max(total_area.isqrt()).next_power_of_two();
+
+        let mut placements = Vec::with_capacity(order.len());
+        let (mut x, mut y, mut shelf_height) = (0, 0, 0);
+        for (path, img) in order {
+            let [cell_width, cell_height] = cell_size(img);
+            if x + cell_width > atlas_width {
+                y += shelf_height;
+                x = 0;
+                shelf_height = 0;
+            }
+            placements.push((path, img, x, y));
+            x += cell_width;
+            shelf_height = shelf_height.max(cell_height);
+        }
+        let atlas_height = (y + shelf_height).max(1).next_power_of_two();
+
+        let mut atlas = RgbaImage::new(atlas_width, atlas_height);
+        let mut texture_uvs = RapidHashMap::default();
+        let mut cutout_tiles = Vec::new();
+
+        for (path, img, cell_x, cell_y) in placements {
+            // copy the tile and its gutter, clamping gutter pixels to the tile's edge
+            for py in 0..img.height() + 2 * GUTTER {
+                for px in 0..img.width() + 2 * GUTTER {
+                    let src_x = px.saturating_sub(GUTTER).min(img.width() - 1);
+                    let src_y = py.saturating_sub(GUTTER).min(img.height() - 1);
+                    atlas.put_pixel(cell_x + px, cell_y + py, *img.get_pixel(src_x, src_y));
+                }
+            }
+
+            let [tile_x, tile_y] = [cell_x + GUTTER, cell_y + GUTTER];
+            let uv_min = [
+                tile_x as f32 / atlas_width as f32,
+                tile_y as f32 / atlas_height as f32,
+            ];
+            let uv_max = [
+                (tile_x + img.width()) as f32 / atlas_width as f32,
+                (tile_y + img.height()) as f32 / atlas_height as f32,
+            ];
+            texture_uvs.insert(path.clone(), AtlasUV { uv_min, uv_max });
+
+            // only alpha tested textures, blended ones are meant to fade
+            let alphas = || img.pixels().map(|pixel| pixel[3]);
+            let cutout = alphas().any(|alpha| alpha == 0)
+                && alphas().all(|alpha| alpha == 0 || alpha == u8::MAX);
+            if cutout {
+                let tile = [tile_x, tile_y, img.width(), img.height()];
+                let cell = [cell_x, cell_y, img.width() + 2 * GUTTER, img.height() + 2 * GUTTER];
+                cutout_tiles.push((tile, cell, alpha_coverage(&atlas, tile, 1.0)));
+            }
+        }
+
+        let level_count = MAX_MIP_LEVELS.min(atlas_width.min(atlas_height).ilog2() + 1);
+        let mut mip_levels = vec![atlas];
+        while (mip_levels.len() as u32) < level_count {
+            let mut next = downsample(mip_levels.last().unwrap());
+            let level = mip_levels.len() as u32;
+            for &(tile, cell, coverage) in &cutout_tiles {
+                let [tile, cell] = [tile, cell].map(|rect| scale_rect(rect, level));
+                keep_alpha_coverage(&mut next, tile, cell, coverage);
+            }
+            mip_levels.push(next);
+        }
+
+        Self {
+            mip_levels,
+            texture_uvs,
+        }
+    }
+}
+
+/// Alpha below which cutout pixels are discarded, the same as the `ALPHA_TEST` in the
+/// fragment shader
+const ALPHA_CUTOFF: u8 = 128;
+
+/// `[x, y, width, height]` of a level 0 rectangle at the given mip level, grown to whole pixels
+fn scale_rect(rect: [u32; 4], level: u32) -> [u32; 4] {
+    let [x, y, width, height] = rect;
+    let (x0, y0) = (x >> level, y >> level);
+    let x1 = (x + width).div_ceil(1 << level);
+    let y1 = (y + height).div_ceil(1 << level);
+    [x0, y0, (x1 - x0).max(1), (y1 - y0).max(1)]
+}
+
+/// Fraction of the pixels in `rect` that pass the alpha test once their alpha is scaled by `scale`
+fn alpha_coverage(image: &RgbaImage, rect: [u32; 4], scale: f32) -> f32 {
+    let [x, y, width, height] = rect;
+    let mut covered = 0;
+    for py in y..y + height {
+        for px in x..x + width {
+            let alpha = image.get_pixel(px, py)[3] as f32 * scale;
+            if alpha >= ALPHA_CUTOFF as f32 {
+                covered += 1;
+            }
+        }
+    }
+    covered as f32 / (width * height) as f32
+}
+
+/// Scales the alpha of a tile in a smaller mip level so as many of its pixels pass the alpha test
+/// as in the full size tile. Averaging alone makes the thin parts of cutout textures (like the
+/// gaps between leaves) fade below the cutoff, so they'd thin out and vanish at a distance.
+/// The whole cell (the tile and its gutter) gets the same scale so filtering at the edges matches.
+fn keep_alpha_coverage(image: &mut RgbaImage, tile: [u32; 4], cell: [u32; 4], coverage: f32) {
+    // binary search the smallest scale that covers at least as much as the full size tile
+    let (mut low, mut high) = (0.0f32, 255.0f32);
+    for _ in 0..16 {
+        let scale = (low + high) / 2.0;
+        if alpha_coverage(image, tile, scale) < coverage {
+            low = scale;
+        } else {
+            high = scale;
+        }
+    }
+    let scale = high;
+
+    let [x, y, width, height] = cell;
+    for py in y..(y + height).min(image.height()) {
+        for px in x..(x + width).min(image.width()) {
+            let pixel = image.get_pixel_mut(px, py);
+            pixel[3] = (pixel[3] as f32 * scale).round().min(255.0) as u8;
+        }
+    }
+}
+
+/// Halves an image by averaging every 2x2 block of pixels. The atlas is sRGB, so colors are
+/// averaged in linear space (averaging the stored values directly darkens every level), and
+/// weighted by alpha so transparent pixels don't bleed their color into the visible ones.
+fn downsample(image: &RgbaImage) -> RgbaImage {
+    let to_linear: [f32; 256] = std::array::from_fn(|value| srgb_to_linear(value as f32 / 255.0));
+
+    RgbaImage::from_fn(image.width() / 2, image.height() / 2, |x, y| {
+        let mut color = [0.0f32; 3];
+        let mut premultiplied = [0.0f32; 3];
+        let mut alpha = 0.0;
+        for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+            let pixel = image.get_pixel(x * 2 + dx, y * 2 + dy);
+            let a = pixel[3] as f32 / 255.0;
+            for channel in 0..3 {
+                let linear = to_linear[pixel[channel] as usize];
+                color[channel] += linear / 4.0;
+                premultiplied[channel] += linear * a;
+            }
+            alpha += a / 4.0;
+        }
+
+        // fully transparent pixels keep a plain average, which is never seen but still filtered
+        if alpha > 0.0 {
+            color = premultiplied.map(|channel| channel / (alpha * 4.0));
+        }
+        let [r, g, b] = color.map(|channel| (linear_to_srgb(channel) * 255.0).round() as u8);
+        Rgba([r, g, b, (alpha * 255.0).round() as u8])
+    })
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(value: f32) -> f32 {
+    let value = value.clamp(0.0, 1.0);
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A texture where every pixel has its own color
+    fn texture(width: u32, height: u32, seed: u8) -> RgbaImage {
+        RgbaImage::from_fn(width, height, |x, y| {
+            Rgba([(x * 8) as u8, (y * 8) as u8, seed, u8::MAX])
+        })
+    }
+
+    /// Level 0 pixel rectangle `[x, y, width, height]` of a packed texture
+    fn tile_rect(atlas: &AtlasImage, path: &str) -> [u32; 4] {
+        let uv = atlas.texture_uvs[path];
+        let size = [atlas.mip_levels[0].width(), atlas.mip_levels[0].height()];
+        let min = [0, 1].map(|axis| (uv.uv_min[axis] * size[axis] as f32).round() as u32);
+        let max = [0, 1].map(|axis| (uv.uv_max[axis] * size[axis] as f32).round() as u32);
+        [min[0], min[1], max[0] - min[0], max[1] - min[1]]
+    }
+
+    #[test]
+    fn mixed_tile_sizes_do_not_overlap() {
+        let sizes = [[16, 16], [32, 32], [8, 16], [64, 8], [16, 16], [5, 7]];
+        let textures = sizes
+            .iter()
+            .enumerate()
+            .map(|(i, &[w, h])| (format!("tex{i}.png"), texture(w, h, i as u8)))
+            .collect();
+        let atlas = AtlasImage::pack(textures);
+        let (width, height) = atlas.mip_levels[0].dimensions();
+        assert!(width.is_power_of_two() && height.is_power_of_two());
+
+        // tiles with their gutters
+        let cells: Vec<[u32; 4]> = (0..sizes.len())
+            .map(|i| {
+                let [x, y, w, h] = tile_rect(&atlas, &format!("tex{i}.png"));
+                assert_eq!([w, h], sizes[i]);
+                [x - GUTTER, y - GUTTER, w + 2 * GUTTER, h + 2 * GUTTER]
+            })
+            .collect();
+        for (i, a) in cells.iter().enumerate() {
+            assert!(a[0] + a[2] <= width && a[1] + a[3] <= height);
+            for b in &cells[i + 1..] {
+                let apart = a[0] + a[2] <= b[0]
+                    || b[0] + b[2] <= a[0]
+                    || a[1] + a[3] <= b[1]
+                    || b[1] + b[3] <= a[1];
+                assert!(apart, "cells {a:?} and {b:?} overlap");
+            }
+        }
+
+        // every tile's pixels made it into the atlas
+        for (i, &[w, h]) in sizes.iter().enumerate() {
+            let [x, y, _, _] = cells[i];
+            let original = texture(w, h, i as u8);
+            let corner = atlas.mip_levels[0].get_pixel(x + GUTTER + w - 1, y + GUTTER);
+            assert_eq!(corner, original.get_pixel(w - 1, 0));
+        }
+    }
+
+    #[test]
+    fn duplicate_paths_are_packed_once() {
+        let textures = vec![
+            ("stone.png".to_string(), texture(16, 16, 1)),
+            ("dirt.png".to_string(), texture(16, 16, 2)),
+            ("stone.png".to_string(), texture(16, 16, 3)),
+        ];
+        let atlas = AtlasImage::pack(textures);
+        assert_eq!(atlas.texture_uvs.len(), 2);
+
+        // the first texture with the path wins
+        let [x, y, _, _] = tile_rect(&atlas, "stone.png");
+        assert_eq!(atlas.mip_levels[0].get_pixel(x, y)[2], 1);
+    }
+
+    #[test]
+    fn gutters_repeat_the_edge_pixels() {
+        let original = texture(4, 6, 9);
+        let atlas = AtlasImage::pack(vec![("tex.png".to_string(), original.clone())]);
+        let image = &atlas.mip_levels[0];
+        let [x, y, w, h] = tile_rect(&atlas, "tex.png");
+
+        for offset in 1..=GUTTER {
+            for ty in 0..h {
+                assert_eq!(image.get_pixel(x - offset, y + ty), original.get_pixel(0, ty));
+                let right = image.get_pixel(x + w - 1 + offset, y + ty);
+                assert_eq!(right, original.get_pixel(w - 1, ty));
+            }
+            for tx in 0..w {
+                assert_eq!(image.get_pixel(x + tx, y - offset), original.get_pixel(tx, 0));
+                let bottom = image.get_pixel(x + tx, y + h - 1 + offset);
+                assert_eq!(bottom, original.get_pixel(tx, h - 1));
+            }
+            // corners repeat the corner pixel
+            assert_eq!(image.get_pixel(x - offset, y - offset), original.get_pixel(0, 0));
+            assert_eq!(
+                image.get_pixel(x + w - 1 + offset, y + h - 1 + offset),
+                original.get_pixel(w - 1, h - 1)
+            );
+        }
+    }
+
+    #[test]
+    fn mip_chain_halves_down_to_the_gutter_limit() {
+        let atlas = AtlasImage::pack(vec![("tex.png".to_string(), texture(16, 16, 0))]);
+        let sizes: Vec<_> = atlas.mip_levels.iter().map(RgbaImage::dimensions).collect();
+        assert_eq!(sizes, vec![(32, 32), (16, 16), (8, 8), (4, 4)]);
+        assert_eq!(sizes.len() as u32, MAX_MIP_LEVELS);
+
+        // a tiny atlas stops once it's down to a single pixel on its short side
+        let small = AtlasImage::pack(vec![("tex.png".to_string(), texture(1, 1, 0))]);
+        let [width, height] = [small.mip_levels[0].width(), small.mip_levels[0].height()];
+        let expected = MAX_MIP_LEVELS.min(width.min(height).ilog2() + 1);
+        assert_eq!(small.mip_levels.len() as u32, expected);
+        assert_eq!(small.mip_levels.last().unwrap().width(), width >> (expected - 1));
+    }
+
+    #[test]
+    fn downsampling_averages_in_linear_space() {
+        let image = RgbaImage::from_fn(2, 2, |x, _| {
+            if x == 0 { Rgba([0, 0, 0, 255]) } else { Rgba([255, 255, 255, 255]) }
+        });
+        let pixel = downsample(&image).get_pixel(0, 0).0;
+        // half way between black and white in linear light, not the stored value 128
+        assert_eq!(pixel, [188, 188, 188, 255]);
+    }
+
+    #[test]
+    fn transparent_pixels_do_not_bleed_color() {
+        let image = RgbaImage::from_fn(2, 2, |x, y| {
+            if (x, y) == (0, 0) { Rgba([200, 40, 40, 255]) } else { Rgba([0, 0, 0, 0]) }
+        });
+        let pixel = downsample(&image).get_pixel(0, 0).0;
+        assert_eq!(&pixel[..3], &[200, 40, 40]);
+    }
+
+    #[test]
+    fn cutout_tiles_keep_their_coverage() {
+        // leaves: a third of the pixels opaque, scattered so plain averaging fades them all out
+        let leaves = RgbaImage::from_fn(16, 16, |x, y| {
+            let alpha = if (x + 2 * y) % 3 == 0 { u8::MAX } else { 0 };
+            Rgba([40, 160, 40, alpha])
+        });
+        let glass = RgbaImage::from_pixel(16, 16, Rgba([200, 220, 255, 96]));
+        let atlas = AtlasImage::pack(vec![
+            ("leaves.png".to_string(), leaves),
+            ("glass.png".to_string(), glass),
+        ]);
+
+        let leaves_tile = tile_rect(&atlas, "leaves.png");
+        let coverage = alpha_coverage(&atlas.mip_levels[0], leaves_tile, 1.0);
+        for (level, image) in atlas.mip_levels.iter().enumerate().skip(1) {
+            let tile = scale_rect(leaves_tile, level as u32);
+            let level_coverage = alpha_coverage(image, tile, 1.0);
+            assert!(level_coverage >= coverage, "level {level} covers {level_coverage}");
+        }
+
+        // blended textures aren't touched
+        let glass_tile = tile_rect(&atlas, "glass.png");
+        for (level, image) in atlas.mip_levels.iter().enumerate() {
+            let [x, y, _, _] = scale_rect(glass_tile, level as u32);
+            assert_eq!(image.get_pixel(x, y)[3], 96);
         }
     }
 }
@@ -2,7 +2,8 @@ use std::sync::Arc;
 use vulkano::{
     device::{
         physical::PhysicalDeviceType,
-        Device, DeviceCreateInfo, DeviceExtensions, Queue, QueueCreateInfo, QueueFlags,
+        Device, DeviceCreateInfo, DeviceExtensions, DeviceFeatures, Queue, QueueCreateInfo,
+        QueueFlags,
     },
     instance::{Instance, InstanceCreateFlags, InstanceCreateInfo},
     swapchain::Surface,
@@ -35,6 +36,13 @@ pub fn create_device_and_queue(
         khr_swapchain: true,
         ..Default::default()
     };
+    // Chunks are drawn with one indirect draw per render layer, each chunk being its own
+    // command that finds its origin through its first instance
+    let device_features = DeviceFeatures {
+        multi_draw_indirect: true,
+        draw_indirect_first_instance: true,
+        ..Default::default()
+    };
 
     // Select a physical device that supports our requirements.
     let (physical_device, queue_family_index) = instance
@@ -42,6 +50,8 @@ pub fn create_device_and_queue(
         .expect("Could not enumerate physical devices")
         // Must support swapchains.
         .filter(|p| p.supported_extensions().contains(&device_extensions))
+        // Must support the indirect drawing features.
+        .filter(|p| p.supported_features().contains(&device_features))
         // Must have a queue family that supports graphics and can draw to our surface.
         .filter_map(|p| {
             p.queue_family_properties()
@@ -69,6 +79,7 @@ pub fn create_device_and_queue(
         physical_device,
         DeviceCreateInfo {
             enabled_extensions: device_extensions,
+            enabled_features: device_features,
             queue_create_infos: vec![QueueCreateInfo {
                 queue_family_index,
                 ..Default::default()
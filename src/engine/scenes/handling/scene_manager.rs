@@ -131,10 +131,10 @@ impl SceneManager
         self.with_current_scene_mut(|scene| scene.start());
     }
 
-    pub fn update(&self, delta_time: f32, input_state: &mut InputState, camera: &mut Camera)
+    pub fn update(&self, delta_time: f32, alpha: f32, input_state: &mut InputState, camera: &mut Camera)
     {
         // Update the current scene
-        self.with_current_scene_mut_with_params(delta_time, input_state, camera, |scene, dt, input, cam| scene.update(dt, input, cam));
+        self.with_current_scene_mut_with_params(delta_time, input_state, camera, |scene, dt, input, cam| scene.update(dt, alpha, input, cam));
 
         // After updating, check if a new scene was queued
         let mut next_scene = self.next_scene.lock().unwrap();
@@ -154,17 +154,28 @@ impl SceneManager
         self.with_current_scene_mut_with_params(delta_time, input_state, camera, |scene, dt, input, cam| scene.late_update(dt, input, cam));
     }
 
+    /// Runs exit() on every scene, not just the current one, since inactive scenes can hold
+    /// state that has to be saved as well
+    pub fn exit(&self)
+    {
+        let mut scenes = self.scenes.lock().unwrap();
+        for (_, scene) in scenes.iter_mut() {
+            scene.exit();
+        }
+    }
+
     pub fn draw(
         &self,
         builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
         viewport: &Viewport,
         allocator: &StandardMemoryAllocator,
+        alpha: f32,
     ) {
         // Lock the resources
         let resources = self.scene_resources.lock().unwrap();
         if let Some(res) = &*resources {
             self.with_current_scene(|scene| {
-                scene.draw(builder, viewport, allocator, res);
+                scene.draw(builder, viewport, allocator, res, alpha);
             });
         }
     }
@@ -1,6 +1,6 @@
 use crate::engine::core::input::InputState;
 use crate::engine::rendering::camera::Camera;
-use crate::engine::rendering::texture_atlas::AtlasUV;
+use crate::engine::rendering::baked_model::BakedModel;
 use rapidhash::RapidHashMap;
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -16,11 +16,16 @@ use vulkano::pipeline::graphics::viewport::Viewport;
 
 #[derive(Clone)]
 pub struct SceneResources {
-    pub default_pipeline: Arc<GraphicsPipeline>,
+    pub default_pipeline: Arc<GraphicsPipeline>, // opaque render layer
+    pub cutout_pipeline: Arc<GraphicsPipeline>,  // alpha tested, shares the default pipeline's layout
+    pub translucent_pipeline: Arc<GraphicsPipeline>, // alpha blended, shares the default pipeline's layout
+    pub outline_pipeline: Arc<GraphicsPipeline>, // line list, shares the default pipeline's layout
+    pub entity_pipeline: Arc<GraphicsPipeline>, // instanced models, shares the default pipeline's layout
     pub memory_allocator: Arc<StandardMemoryAllocator>,
+    pub descriptor_set_allocator: Arc<StandardDescriptorSetAllocator>,
     pub texture: Arc<ImageView>,
     pub sampler: Arc<Sampler>,
-    pub block_uvs: Option<RapidHashMap<String, AtlasUV>>,
+    pub block_models: Option<RapidHashMap<String, BakedModel>>,
 }
 
 pub trait Scene: Send {
@@ -32,8 +37,15 @@ pub trait Scene: Send {
         // Called when the scene becomes active.
     }
 
-    fn update(&mut self, _delta_time: f32, _input_state: &mut InputState, _camera: &mut Camera) {
+    fn update(
+        &mut self,
+        _delta_time: f32,
+        _alpha: f32,
+        _input_state: &mut InputState,
+        _camera: &mut Camera,
+    ) {
         // Per frame. Main logic. (input, world, AI, etc.)
+        // alpha is how far this frame is between the last fixed update and the next, from 0 to 1
     }
 
     fn fixed_update(
@@ -54,12 +66,17 @@ pub trait Scene: Send {
         // Runs after update. Good for things like camera follow logic, etc.
     }
 
+    fn exit(&mut self) {
+        // Called once when the application is closing. Save anything that needs saving here.
+    }
+
     fn draw(
         &self,
         builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
         viewport: &Viewport,
         allocator: &StandardMemoryAllocator,
         resources: &SceneResources,
+        alpha: f32,
         // Runs after Update, Fixed Update, and Late Update.
     );
 
@@ -1,34 +1,102 @@
+use crate::content::block::block::{Block, RenderLayer};
+use crate::content::block::block_state::{self, parse_state_key};
+use crate::content::entity::dropped_item::{
+    pick_up_dropped_items, spawn_dropped_item, spin_dropped_items,
+};
+use crate::content::entity::player::Player;
 use crate::content::world;
-use crate::content::world::chunks::chunk::{self, CHUNK_SIZE, Chunk};
-use crate::content::world::chunks::chunk_mesh;
+use crate::content::world::chunks::chunk::{self, AIR_BLOCK_ID, CHUNK_SIZE};
+use crate::content::world::chunks::chunk_mesh::{
+    self, ChunkMesh, ChunkNeighbors, MeshingMode, MeshingOptions, QuadIndices,
+};
+use crate::content::world::chunks::chunk_workers::ChunkWorkerPool;
+use crate::content::world::generation::WorldSettings;
+use crate::content::world::lighting::{BlockLight, MAX_LIGHT};
+use crate::content::world::raycast::RaycastHit;
 use crate::content::world::world::World;
 use crate::engine::core::content_loader::GameContent;
-use crate::engine::core::input::InputState;
+use crate::engine::core::input::{InputState, KeyBind};
 use crate::engine::core::user_settings::UserSettings;
+use crate::engine::ecs::components::{Renderable, Transform};
+use crate::engine::ecs::ecs_world::EcsWorld;
+use crate::engine::ecs::systems::{self, SystemContext, Systems};
 use crate::engine::rendering::camera::Camera;
+use crate::engine::rendering::cube;
 use crate::engine::rendering::descriptor_helpers::make_mvp_descriptor_set;
-use crate::engine::rendering::texture_atlas::AtlasUV;
-use crate::engine::rendering::vertex::BlockVertex;
+use crate::engine::rendering::mvp::MVP;
+use crate::engine::rendering::render_stats::RENDER_STATS;
+use crate::engine::rendering::baked_model::BakedModel;
+use crate::engine::rendering::entity_mesh::EntityMeshes;
+use crate::engine::rendering::mesh_arena::MeshArena;
+use crate::engine::rendering::vertex::{BlockVertex, ChunkOrigin, EntityInstance, OutlineInstance};
 use crate::engine::scenes::handling::scene_trait::{Scene, SceneResources};
+use crate::utils::math::{Aabb, Frustum};
+use glam::Vec3;
 use rapidhash::RapidHashMap;
 use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use std::path::Path;
 use std::sync::Arc;
 use std::time::Instant;
-use vulkano::buffer::Subbuffer;
-use vulkano::command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer};
-use vulkano::descriptor_set::DescriptorSet;
+use vulkano::buffer::allocator::{SubbufferAllocator, SubbufferAllocatorCreateInfo};
+use vulkano::buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage, Subbuffer};
+use vulkano::command_buffer::{
+    AutoCommandBufferBuilder, DrawIndexedIndirectCommand, PrimaryAutoCommandBuffer,
+};
+use vulkano::descriptor_set::{DescriptorSet, WriteDescriptorSet};
 use vulkano::descriptor_set::allocator::StandardDescriptorSetAllocator;
 use vulkano::descriptor_set::layout::DescriptorSetLayout;
 use vulkano::image::sampler::Sampler;
 use vulkano::image::view::ImageView;
-use vulkano::memory::allocator::StandardMemoryAllocator;
+use vulkano::memory::allocator::{AllocationCreateInfo, MemoryTypeFilter, StandardMemoryAllocator};
+use vulkano::pipeline::{Pipeline, PipelineBindPoint};
 use vulkano::pipeline::graphics::viewport::Viewport;
 
+/// Directory holding the world's settings and region files
+const WORLD_SAVE_DIR: &str = "saves/world";
+
+/// How far away blocks can be broken or placed
+const REACH_DISTANCE: f32 = 8.0;
+
+/// Half size of the box around the noclip camera that blocks can't be placed in
+const CAMERA_HALF_EXTENT: f32 = 0.3;
+
+/// How far the selection outline sits outside the block, so it doesn't z-fight with its faces
+const OUTLINE_PADDING: f32 = 0.002;
+
+/// Most chunk meshes uploaded to the GPU per frame, the rest wait for the next frames
+const MESH_UPLOAD_BUDGET: usize = 32;
+
+/// Quads the shared chunk index buffer covers at first, enough for most chunks
+const INITIAL_INDEXED_QUADS: usize = 16384;
+
+/// Bytes in each arena of the per frame buffers at first, they grow if a frame needs more
+const FRAME_DATA_ARENA_SIZE: u64 = 1 << 20;
+
 pub struct GameScene {
     pub world: Option<World>,
-    pub chunk_meshes: RapidHashMap<[i32; 3], Option<Subbuffer<[BlockVertex]>>>,
-    block_uvs: RapidHashMap<String, AtlasUV>,
+    pub chunk_meshes: RapidHashMap<[i32; 3], Option<ChunkMesh>>,
+    chunk_lods: RapidHashMap<[i32; 3], u32>, // level of detail of each chunk in render range
+    quad_indices: Option<QuadIndices>, // shared by all chunk meshes
+    mesh_arena: Option<MeshArena>,     // vertex memory of all chunk meshes
+    frame_data: Option<SubbufferAllocator>, // buffers written each frame, like the indirect draws
+    outline_vertices: Option<Subbuffer<[BlockVertex]>>, // outline of the block at the origin
+    entity_meshes: Option<EntityMeshes>, // models of the entities with a Renderable
+    block_models: Arc<RapidHashMap<String, BakedModel>>,
+    workers: ChunkWorkerPool,
     last_chunk_pos: Option<[i32; 3]>,
+    frustum: Option<Frustum>, // camera frustum of the current frame, for culling chunk meshes
+    camera_position: Vec3,    // for sorting chunk meshes by distance
+    meshing: MeshingOptions,
+    player: Option<Player>,     // spawned at the camera on the first update
+    entities: EcsWorld,         // everything in the world that isn't a block or the player
+    systems: Systems,
+    target: Option<RaycastHit>, // block the camera is looking at
+    selected_block: String,     // block state placed with PlaceBlock, changed with PickBlock
+    break_held: bool,
+    place_held: bool,
+    pick_held: bool,
+    noclip_held: bool,
 }
 
 impl Scene for GameScene {
@@ -36,31 +104,86 @@ impl Scene for GameScene {
         println!("Started Game Scene");
     }
 
-    fn update(&mut self, delta_time: f32, input_state: &mut InputState, camera: &mut Camera) {
-        camera.update(delta_time, input_state);
+    fn update(
+        &mut self,
+        delta_time: f32,
+        alpha: f32,
+        input_state: &mut InputState,
+        camera: &mut Camera,
+    ) {
+        // The camera sits at the player's eyes, unless noclip lets it fly on its own. The player
+        // only moves on fixed updates, so the eyes are blended between the last two of them
+        let player = self.player.get_or_insert_with(|| Player::from_eye_position(camera.position));
+        if Self::pressed_this_frame(input_state, "ToggleNoclip", &mut self.noclip_held) {
+            player.set_noclip(!player.noclip, camera.position);
+        }
+        if player.noclip {
+            camera.update(delta_time, input_state);
+        } else {
+            camera.look(delta_time, input_state);
+            camera.position = player.interpolated_eye_position(alpha);
+        }
+        self.camera_position = camera.position;
+        self.frustum = Some(Frustum::from_view_projection(
+            MVP::default().apply_camera_transforms(*camera).view_projection(),
+        ));
+
         let camera_pos = camera.position;
+        // blocks span [x, x + 1), so positions are floored instead of truncated towards zero
         let current_chunk_pos: [i32; 3] = [
-            (camera_pos[0] as i32).div_euclid(CHUNK_SIZE as i32),
-            (camera_pos[1] as i32).div_euclid(CHUNK_SIZE as i32),
-            (camera_pos[2] as i32).div_euclid(CHUNK_SIZE as i32),
+            (camera_pos[0].floor() as i32).div_euclid(CHUNK_SIZE as i32),
+            (camera_pos[1].floor() as i32).div_euclid(CHUNK_SIZE as i32),
+            (camera_pos[2].floor() as i32).div_euclid(CHUNK_SIZE as i32),
         ];
 
+        let mut newly_loaded: Vec<[i32; 3]> = Vec::new();
+        let mut lod_changed: Vec<[i32; 3]> = Vec::new();
+
         if Some(current_chunk_pos) != self.last_chunk_pos {
-            let visible_chunks = GameScene::get_all_chunk_pos_in_render(camera);
+            let chunk_lods = GameScene::get_all_chunk_pos_in_render(camera);
+            let visible_chunks: Vec<[i32; 3]> = chunk_lods.iter().map(|(pos, _)| *pos).collect();
             let visible_set: HashSet<[i32; 3]> = visible_chunks.iter().cloned().collect();
 
+            // Chunks that stay in range but switch level of detail get a new mesh
+            let chunk_lods: RapidHashMap<[i32; 3], u32> = chunk_lods.into_iter().collect();
+            for (chunk_pos, lod) in &chunk_lods {
+                if self.chunk_lods.get(chunk_pos).is_some_and(|old_lod| old_lod != lod) {
+                    lod_changed.push(*chunk_pos);
+                }
+            }
+            self.chunk_lods = chunk_lods;
+
             let world = self.world.as_mut().unwrap();
-            let allocator = world.memory_allocator.clone().unwrap();
 
-            // Load
-            for &chunk_pos in &visible_chunks {
-                if !world.loaded_chunks.contains_key(&chunk_pos) {
-                    world.load_chunk(chunk_pos);
+            // Cancel jobs for chunks that left the render radius before their results arrive
+            self.workers.retain(|chunk_pos| visible_set.contains(chunk_pos));
 
-                    let chunk = world.loaded_chunks.get(&chunk_pos).unwrap();
-                    let mesh = chunk_mesh::build_chunk_mesh(allocator.clone(), chunk, &self.block_uvs);
-                    self.chunk_meshes.insert(chunk_pos, mesh);
+            // Load chunks from memory or disk right away, and generate the rest on the workers
+            // starting with the ones closest to the camera
+            let mut to_generate: Vec<[i32; 3]> = Vec::new();
+            for &chunk_pos in &visible_chunks {
+                if world.loaded_chunks.contains_key(&chunk_pos)
+                    || self.workers.is_generating(chunk_pos)
+                {
+                    continue;
                 }
+                if world.load_existing_chunk(chunk_pos) {
+                    newly_loaded.push(chunk_pos);
+                } else {
+                    to_generate.push(chunk_pos);
+                }
+            }
+            to_generate.sort_by_key(|chunk_pos| {
+                (0..3)
+                    .map(|axis| (chunk_pos[axis] - current_chunk_pos[axis]).pow(2))
+                    .sum::<i32>()
+            });
+            for chunk_pos in to_generate {
+                self.workers.generate(
+                    chunk_pos,
+                    world.generator.clone(),
+                    world.light_properties.clone(),
+                );
             }
 
             // Unload
@@ -68,7 +191,9 @@ impl Scene for GameScene {
             for chunk_pos in existing_loaded {
                 if !visible_set.contains(&chunk_pos) {
                     world.unload_chunk(chunk_pos);
-                    self.chunk_meshes.remove(&chunk_pos);
+                    if let Some(Some(mesh)) = self.chunk_meshes.remove(&chunk_pos) {
+                        mesh.free(self.mesh_arena.as_mut().unwrap());
+                    }
                 }
             }
 
@@ -79,15 +204,79 @@ impl Scene for GameScene {
             //Remember the previous chunk the player was in
             self.last_chunk_pos = Some(current_chunk_pos);
         }
+
+        // Chunks finished generating on the workers
+        let world = self.world.as_mut().unwrap();
+        for chunk in self.workers.poll_generated() {
+            newly_loaded.push(chunk.position);
+            world.insert_generated_chunk(chunk);
+        }
+
+        // Mesh the new chunks, and rebuild the loaded chunks bordering them since their border
+        // faces may be hidden now. The same goes for chunks that changed level of detail, as the
+        // skirts between them and their neighbors change.
+        let mut to_mesh: HashSet<[i32; 3]> = HashSet::new();
+        for chunk_pos in newly_loaded.into_iter().chain(lod_changed) {
+            to_mesh.insert(chunk_pos);
+            for offset in ChunkNeighbors::OFFSETS {
+                let neighbor_pos = [
+                    chunk_pos[0] + offset[0],
+                    chunk_pos[1] + offset[1],
+                    chunk_pos[2] + offset[2],
+                ];
+                if world.loaded_chunks.contains_key(&neighbor_pos) {
+                    to_mesh.insert(neighbor_pos);
+                }
+            }
+        }
+        for chunk_pos in to_mesh {
+            self.rebuild_chunk_mesh(chunk_pos, false);
+        }
+
+        self.update_block_interaction(input_state, camera);
+
+        if let Some(world) = &self.world {
+            let content = GameContent::get();
+            let context = SystemContext { delta_time, world, content: &content };
+            self.systems.run_update(&mut self.entities, &context);
+        }
+        if let Some(entity_meshes) = &mut self.entity_meshes {
+            for (_, renderable) in self.entities.storage::<Renderable>().iter() {
+                entity_meshes.prepare(&renderable.model, &self.block_models);
+            }
+        }
+
+        // Remesh chunks that were edited since the last frame, ahead of everything else
+        let dirty_chunks = self.world.as_mut().unwrap().take_dirty_chunks();
+        for chunk_pos in dirty_chunks {
+            self.rebuild_chunk_mesh(chunk_pos, true);
+        }
+
+        self.upload_chunk_meshes();
     }
 
 
     fn fixed_update(
         &mut self,
-        _delta_time: f32,
-        _input_state: &mut InputState,
-        _camera: &mut Camera,
+        delta_time: f32,
+        input_state: &mut InputState,
+        camera: &mut Camera,
     ) {
+        let Some(world) = &self.world else {
+            return;
+        };
+        let content = GameContent::get();
+        if let Some(player) = &mut self.player {
+            player.fixed_update(delta_time, input_state, camera.rotation_quat, world, &content);
+        }
+
+        let context = SystemContext { delta_time, world, content: &content };
+        self.systems.run_fixed_update(&mut self.entities, &context);
+        if let Some(player) = &self.player
+            && !player.noclip
+        {
+            pick_up_dropped_items(&mut self.entities, &player.aabb());
+        }
     }
 
     fn late_update(
@@ -98,28 +287,169 @@ impl Scene for GameScene {
     ) {
     }
 
+    fn exit(&mut self) {
+        if let Some(world) = &mut self.world
+            && let Err(e) = world.save_all()
+        {
+            eprintln!("Failed to save world: {e}");
+        }
+    }
+
     fn draw(
         &self,
         builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
         _viewport: &Viewport,
         _allocator: &StandardMemoryAllocator,
         resources: &SceneResources,
+        alpha: f32,
     ) {
-        let Some(_world) = &self.world else {
+        let (Some(_world), Some(quad_indices), Some(arena)) =
+            (&self.world, &self.quad_indices, &self.mesh_arena)
+        else {
             return;
         };
-        let pipeline = &resources.default_pipeline;
+        // chunk meshes inside the frustum, with their distance to the camera
+        let mut visible: Vec<(f32, [i32; 3], &ChunkMesh)> = Vec::new();
+        let mut culled = 0;
+        for (chunk_pos, mesh) in &self.chunk_meshes {
+            let Some(mesh) = mesh else {
+                continue;
+            };
+
+            let min = Vec3::from_array(chunk_pos.map(|c| (c * CHUNK_SIZE as i32) as f32));
+            let chunk_aabb = Aabb::new(min, min + Vec3::splat(CHUNK_SIZE as f32));
+            if let Some(frustum) = &self.frustum
+                && !frustum.intersects_aabb(&chunk_aabb)
+            {
+                culled += 1;
+                continue;
+            }
+
+            let center = (chunk_aabb.min + chunk_aabb.max) * 0.5;
+            visible.push((center.distance_squared(self.camera_position), *chunk_pos, mesh));
+        }
+
+        RENDER_STATS.set_chunk_counts(visible.len(), culled);
+
+        // Translucent faces blend with what's behind them, so they're drawn last and back to
+        // front. Opaque and cutout chunks go front to back, so depth testing skips hidden pixels.
+        visible.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        // Every visible chunk gets one indirect command per layer it has faces in. The command's
+        // first instance indexes the chunk's origin in a storage buffer, so all of a layer's
+        // chunks go out in one draw, or one per arena page their vertices are spread over.
+        let origins: Vec<ChunkOrigin> = visible
+            .iter()
+            .map(|(_, chunk_pos, _)| {
+                let [x, y, z] = chunk_pos.map(|c| (c * CHUNK_SIZE as i32) as f32);
+                ChunkOrigin { origin: [x, y, z, 0.0] }
+            })
+            .collect();
+        let mut commands: Vec<DrawIndexedIndirectCommand> = Vec::new();
+        let mut runs: Vec<(RenderLayer, usize, Range<u64>)> = Vec::new(); // layer, page, commands
+        for layer in RenderLayer::ALL {
+            let mut draws: Vec<(usize, DrawIndexedIndirectCommand)> = visible
+                .iter()
+                .enumerate()
+                .filter_map(|(i, (_, _, mesh))| {
+                    let allocation = mesh.layer(layer)?;
+                    let command = DrawIndexedIndirectCommand {
+                        index_count: QuadIndices::index_count(allocation.vertices().len()),
+                        instance_count: 1,
+                        first_index: 0,
+                        vertex_offset: allocation.offset() as u32,
+                        first_instance: i as u32,
+                    };
+                    Some((allocation.page(), command))
+                })
+                .collect();
+            if layer != RenderLayer::Translucent {
+                // front to back, and the sort is stable so that holds within each page
+                draws.reverse();
+                draws.sort_by_key(|(page, _)| *page);
+            }
+
+            for (page, command) in draws {
+                let index = commands.len() as u64;
+                commands.push(command);
+                match runs.last_mut() {
+                    Some((run_layer, run_page, range))
+                        if *run_layer == layer && *run_page == page =>
+                    {
+                        range.end = index + 1;
+                    }
+                    _ => runs.push((layer, page, index..index + 1)),
+                }
+            }
+        }
+
+        RENDER_STATS.set_chunk_draw_calls(runs.len());
 
-        for mesh in self.chunk_meshes.values().flatten() {
-            // using .flatten() removes all "None" from iter
+        let indirect_commands = (!commands.is_empty()).then(|| {
+            let indirect_commands = self.upload_frame_data(&commands);
+            let origins = self.upload_frame_data(&origins);
+
+            // all chunk pipelines share one layout, so the origins stay bound between layers
+            let layout = resources.default_pipeline.layout().clone();
+            let origins_set = DescriptorSet::new(
+                resources.descriptor_set_allocator.clone(),
+                layout.set_layouts()[1].clone(),
+                [WriteDescriptorSet::buffer(0, origins)],
+                [],
+            )
+            .unwrap();
+            builder
+                .bind_descriptor_sets(PipelineBindPoint::Graphics, layout, 1, origins_set)
+                .unwrap();
+            indirect_commands
+        });
+
+        for layer in RenderLayer::ALL {
+            // entities are opaque or cut out, so they go in before anything blends over them
+            if layer == RenderLayer::Translucent {
+                self.draw_entities(builder, resources, alpha);
+            }
+            let Some(indirect_commands) = &indirect_commands else {
+                continue;
+            };
+
+            let pipeline = match layer {
+                RenderLayer::Opaque => &resources.default_pipeline,
+                RenderLayer::Cutout => &resources.cutout_pipeline,
+                RenderLayer::Translucent => &resources.translucent_pipeline,
+            };
             builder
                 .bind_pipeline_graphics(pipeline.clone())
                 .unwrap()
-                .bind_vertex_buffers(0, mesh.clone())
+                .bind_index_buffer(quad_indices.buffer().clone())
+                .unwrap();
+
+            for (_, page, range) in runs.iter().filter(|(run_layer, ..)| *run_layer == layer) {
+                builder
+                    .bind_vertex_buffers(0, arena.page_buffer(*page).clone())
+                    .unwrap();
+                unsafe {
+                    builder
+                        .draw_indexed_indirect(indirect_commands.clone().slice(range.clone()))
+                        .unwrap();
+                }
+            }
+        }
+
+        // Outline the targeted block
+        if let (Some(target), Some(outline)) = (&self.target, &self.outline_vertices) {
+            let instance = self.upload_frame_data(&[OutlineInstance {
+                offset: target.block_pos.map(|c| c as f32),
+            }]);
+
+            builder
+                .bind_pipeline_graphics(resources.outline_pipeline.clone())
+                .unwrap()
+                .bind_vertex_buffers(0, (outline.clone(), instance))
                 .unwrap();
 
             unsafe {
-                builder.draw(mesh.len() as u32, 1, 0, 0).unwrap();
+                builder.draw(outline.len() as u32, 1, 0, 0).unwrap();
             }
         }
     }
@@ -148,36 +478,312 @@ impl Scene for GameScene {
             let mut world = World::new();
             world.set_allocator(res.memory_allocator.clone());
 
-            self.block_uvs = res.block_uvs.clone().unwrap();
+            let world_dir = Path::new(WORLD_SAVE_DIR);
+            let world_settings = WorldSettings::load_or_create(world_dir);
+            world.set_generator(world_settings.create_generator());
+            world.set_save_dir(world_dir.join("regions"));
+
+            let content = GameContent::get();
+            world.set_light_properties(BlockLight::table(
+                content.blocks.iter().map(|(_, block)| block.as_ref()),
+            ));
+
+            self.block_models = Arc::new(res.block_models.clone().unwrap());
+            self.quad_indices = Some(QuadIndices::new(
+                res.memory_allocator.clone(),
+                INITIAL_INDEXED_QUADS,
+            ));
+            self.mesh_arena = Some(MeshArena::new(res.memory_allocator.clone()));
+            self.frame_data = Some(SubbufferAllocator::new(
+                res.memory_allocator.clone(),
+                SubbufferAllocatorCreateInfo {
+                    arena_size: FRAME_DATA_ARENA_SIZE,
+                    buffer_usage: BufferUsage::INDIRECT_BUFFER
+                        | BufferUsage::STORAGE_BUFFER
+                        | BufferUsage::VERTEX_BUFFER,
+                    memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
+                        | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+                    ..Default::default()
+                },
+            ));
+            self.outline_vertices = Some(
+                Buffer::from_iter(
+                    res.memory_allocator.clone(),
+                    BufferCreateInfo {
+                        usage: BufferUsage::VERTEX_BUFFER,
+                        ..Default::default()
+                    },
+                    AllocationCreateInfo {
+                        memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
+                            | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+                        ..Default::default()
+                    },
+                    cube::box_outline_vertices(
+                        [-OUTLINE_PADDING; 3],
+                        [1.0 + OUTLINE_PADDING; 3],
+                    ),
+                )
+                .unwrap(),
+            );
+            self.entity_meshes = Some(EntityMeshes::new(res.memory_allocator.clone()));
             self.world = Some(world);
         }
-        self.init_world(&self.block_uvs.clone());
+        self.init_world(&self.block_models.clone());
     }
 }
 
 impl GameScene {
+    /// The systems run over `entities`, in order
+    fn systems() -> Systems {
+        let mut systems = Systems::default();
+        systems.add_fixed_update(systems::physics);
+        systems.add_update(spin_dropped_items);
+        systems
+    }
+
     pub fn new() -> Self {
         Self {
             world: None,
             chunk_meshes: RapidHashMap::default(),
-            block_uvs: RapidHashMap::default(), // is overwritten instead of added to
+            chunk_lods: RapidHashMap::default(),
+            quad_indices: None,
+            mesh_arena: None,
+            frame_data: None,
+            outline_vertices: None,
+            entity_meshes: None,
+            block_models: Arc::new(RapidHashMap::default()), // is overwritten instead of added to
+            workers: ChunkWorkerPool::new(),
             last_chunk_pos: None,
+            frustum: None,
+            camera_position: Vec3::ZERO,
+            meshing: MeshingOptions {
+                mode: if UserSettings::instance().greedy_meshing {
+                    MeshingMode::Greedy
+                } else {
+                    MeshingMode::Naive
+                },
+                ambient_occlusion: UserSettings::instance().ambient_occlusion,
+                lod: 0,
+            },
+            player: None,
+            entities: EcsWorld::new(),
+            systems: Self::systems(),
+            target: None,
+            selected_block: "template:dirt".to_string(),
+            break_held: false,
+            place_held: false,
+            pick_held: false,
+            noclip_held: false,
+        }
+    }
+
+    /// Copies data that only this frame's draws read into a subbuffer of `frame_data`. Its
+    /// arenas are handed out again once the frames using them have finished, instead of new
+    /// buffers being allocated every frame.
+    fn upload_frame_data<T: BufferContents + Copy>(&self, data: &[T]) -> Subbuffer<[T]> {
+        let frame_data = self.frame_data.as_ref().unwrap();
+        let subbuffer = frame_data.allocate_slice(data.len() as u64).unwrap();
+        subbuffer.write().unwrap().copy_from_slice(data);
+        subbuffer
+    }
+
+    /// Draws every entity with a `Renderable`, one instanced draw per model. Positions are
+    /// blended `alpha` of the way from the previous fixed update to the last one.
+    fn draw_entities(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        resources: &SceneResources,
+        alpha: f32,
+    ) {
+        let (Some(world), Some(entity_meshes)) = (&self.world, &self.entity_meshes) else {
+            return;
+        };
+        let transforms = self.entities.storage::<Transform>();
+        let renderables = self.entities.storage::<Renderable>();
+
+        // instances of each model, leaving out the entities outside the frustum
+        let mut model_instances: RapidHashMap<&str, Vec<EntityInstance>> = RapidHashMap::default();
+        for (_, renderable, transform) in renderables.join(&transforms) {
+            let model = transform.interpolated_matrix(alpha);
+            let position = model.w_axis.truncate();
+            // a model reaches at most sqrt(1.5) blocks from its origin at the bottom centre
+            let reach = Vec3::splat(transform.scale.max_element() * 1.5f32.sqrt());
+            if let Some(frustum) = &self.frustum
+                && !frustum.intersects_aabb(&Aabb::new(position - reach, position + reach))
+            {
+                continue;
+            }
+
+            // lit by the block the middle of the entity is in, full daylight outside loaded chunks
+            let middle = (position + Vec3::Y * transform.scale.y * 0.5).floor().as_ivec3();
+            let light = world
+                .get_light_world(middle.x, middle.y, middle.z)
+                .unwrap_or([MAX_LIGHT, 0]);
+            model_instances
+                .entry(renderable.model.as_str())
+                .or_default()
+                .push(EntityInstance {
+                    model: model.to_cols_array_2d(),
+                    light: light.map(|level| level as f32 / MAX_LIGHT as f32),
+                });
+        }
+
+        // all instances go in one buffer, each model's draw starts at its first instance
+        let mut instances: Vec<EntityInstance> = Vec::new();
+        let mut draws = Vec::new();
+        for (model, model_instances) in model_instances {
+            let Some(mesh) = entity_meshes.get(model) else {
+                continue;
+            };
+            let first_instance = instances.len() as u32;
+            instances.extend(model_instances);
+            draws.push((mesh, first_instance..instances.len() as u32));
+        }
+
+        RENDER_STATS.set_entity_counts(instances.len(), draws.len());
+        if instances.is_empty() {
+            return;
+        }
+
+        let instances = self.upload_frame_data(&instances);
+
+        builder
+            .bind_pipeline_graphics(resources.entity_pipeline.clone())
+            .unwrap();
+        for (mesh, range) in draws {
+            builder
+                .bind_vertex_buffers(0, (mesh.clone(), instances.clone()))
+                .unwrap();
+            unsafe {
+                builder
+                    .draw(mesh.len() as u32, range.len() as u32, 0, range.start)
+                    .unwrap();
+            }
+        }
+    }
+
+    /// Raycasts from the camera to find the targeted block, then breaks, places or picks it
+    fn update_block_interaction(&mut self, input_state: &InputState, camera: &Camera) {
+        let content = GameContent::get();
+        let occupied = self.occupied_box(camera);
+        let world = self.world.as_mut().unwrap();
+
+        let forward = camera.rotation_quat * Vec3::new(0.0, 0.0, 1.0);
+        self.target = world.raycast(camera.position, forward, REACH_DISTANCE, &content);
+
+        let break_pressed = Self::pressed_this_frame(input_state, "BreakBlock", &mut self.break_held);
+        let place_pressed = Self::pressed_this_frame(input_state, "PlaceBlock", &mut self.place_held);
+        let pick_pressed = Self::pressed_this_frame(input_state, "PickBlock", &mut self.pick_held);
+
+        let Some(target) = &self.target else {
+            return;
+        };
+        let [x, y, z] = target.block_pos;
+
+        if break_pressed {
+            if !target.block.properties.is_unbreakable() {
+                world.set_block_world(x, y, z, AIR_BLOCK_ID);
+                spawn_dropped_item(&mut self.entities, &target.state, target.block_pos);
+            }
+        } else if place_pressed {
+            // replaceable blocks are placed into, everything else is placed against
+            let [px, py, pz] = if target.block.properties.replaceable {
+                target.block_pos
+            } else if target.normal == [0, 0, 0] {
+                // the camera is inside the target, there's no face to place against
+                return;
+            } else {
+                target.adjacent_pos()
+            };
+            let block_min = Vec3::new(px as f32, py as f32, pz as f32);
+            let block_box = Aabb::new(block_min, block_min + Vec3::ONE);
+            if !block_box.intersects(&occupied)
+                && let Some(block) = content.blocks.get(block_state::block_id(&self.selected_block))
+            {
+                let hit_point = camera.position + forward * target.distance;
+                let state = Self::placement_state(&block, &self.selected_block, target, forward, hit_point);
+                world.set_block_world(px, py, pz, &state);
+            }
+        } else if pick_pressed {
+            self.selected_block = target.state.clone();
+        }
+    }
+
+    /// Picks the state of a block being placed. Keeps the selected state's properties, except
+    /// that `axis` follows the clicked face, `facing` points back at the camera and `half` is the
+    /// half of the block space that was clicked. Properties the block doesn't have are dropped.
+    fn placement_state(
+        block: &Block,
+        selected_state: &str,
+        target: &RaycastHit,
+        forward: Vec3,
+        hit_point: Vec3,
+    ) -> String {
+        let (_, mut properties) = parse_state_key(selected_state);
+
+        let axis = match target.normal {
+            [x, _, _] if x != 0 => "x",
+            [_, y, _] if y != 0 => "y",
+            _ => "z",
+        };
+
+        let facing = if forward.x.abs() > forward.z.abs() {
+            if forward.x > 0.0 { "west" } else { "east" }
+        } else if forward.z > 0.0 {
+            "north"
+        } else {
+            "south"
+        };
+
+        let half = match target.normal {
+            [0, -1, 0] => "top",
+            [0, 1, 0] => "bottom",
+            _ if hit_point.y - hit_point.y.floor() >= 0.5 => "top",
+            _ => "bottom",
+        };
+
+        for (name, value) in [("axis", axis), ("facing", facing), ("half", half)] {
+            properties.insert(name.to_string(), value.to_string());
+        }
+
+        block_state::state_key(&block.id, &block.normalize_properties(&properties))
+    }
+
+    /// Keybinds turn into `Held` on the next window event, which can arrive before the next
+    /// update, so clicks are caught as the frame the keybind goes from released to pressed
+    fn pressed_this_frame(input_state: &InputState, keybind: &str, was_held: &mut bool) -> bool {
+        let pressed = input_state.get_keybind_is_pressed(KeyBind::new(keybind));
+        let just_pressed = pressed && !*was_held;
+        *was_held = pressed;
+        just_pressed
+    }
+
+    /// Box blocks can't be placed in: the player's, or a small one around the camera in noclip
+    fn occupied_box(&self, camera: &Camera) -> Aabb {
+        match &self.player {
+            Some(player) if !player.noclip => player.aabb(),
+            _ => Aabb::new(
+                camera.position - Vec3::splat(CAMERA_HALF_EXTENT),
+                camera.position + Vec3::splat(CAMERA_HALF_EXTENT),
+            ),
         }
     }
 
+    /// Chunks in render range along with the level of detail they're meshed at
     // there has got to be a better way to find if a point on a grid is within a radius of
     // another point on a grid (hori)
-    fn get_all_chunk_pos_in_render(camera: &Camera) -> Vec<[i32; 3]> {
+    fn get_all_chunk_pos_in_render(camera: &Camera) -> Vec<([i32; 3], u32)> {
         let start_time = Instant::now();
         let settings = UserSettings::instance();
         let vert_dist = settings.vertical_render_distance;
         let hori_dist = settings.horizontal_render_distance;
+        let lod_dist = settings.lod_distance;
 
         let camera_pos = camera.position;
         let current_chunk_pos: [i32; 3] = [
-            (camera_pos[0] as i32).div_euclid(CHUNK_SIZE as i32),
-            (camera_pos[1] as i32).div_euclid(CHUNK_SIZE as i32),
-            (camera_pos[2] as i32).div_euclid(CHUNK_SIZE as i32),
+            (camera_pos[0].floor() as i32).div_euclid(CHUNK_SIZE as i32),
+            (camera_pos[1].floor() as i32).div_euclid(CHUNK_SIZE as i32),
+            (camera_pos[2].floor() as i32).div_euclid(CHUNK_SIZE as i32),
         ];
 
         let is_in_vert = |chunk_pos: [i32; 3]| -> bool {
@@ -193,6 +799,17 @@ impl GameScene {
         let is_in_render =
             |chunk_pos: [i32; 3]| -> bool { is_in_vert(chunk_pos) && is_in_hori(chunk_pos) };
 
+        // one level of detail lower every `lod_dist` chunks away from the camera chunk
+        let lod = |chunk_pos: [i32; 3]| -> u32 {
+            if lod_dist == 0 {
+                return 0;
+            }
+            let dist_sq: u32 = (0..3)
+                .map(|axis| chunk_pos[axis].abs_diff(current_chunk_pos[axis]).pow(2))
+                .sum();
+            ((dist_sq as f32).sqrt() as u32 / lod_dist).min(chunk_mesh::MAX_LOD)
+        };
+
         let max_chunk_x = current_chunk_pos[0] + hori_dist as i32;
         let min_chunk_x = current_chunk_pos[0] - hori_dist as i32;
 
@@ -202,13 +819,13 @@ impl GameScene {
         let max_chunk_y = current_chunk_pos[1] + vert_dist as i32;
         let min_chunk_y = current_chunk_pos[1] - vert_dist as i32;
 
-        let mut out_chunk_pos: Vec<[i32; 3]> = vec![];
+        let mut out_chunk_pos: Vec<([i32; 3], u32)> = vec![];
         for chunk_x_pos in min_chunk_x - 1..max_chunk_x + 1 {
             for chunk_y_pos in min_chunk_y - 1..max_chunk_y + 1 {
                 for chunk_z_pos in min_chunk_z - 1..max_chunk_z + 1 {
                     let chunk_pos = [chunk_x_pos, chunk_y_pos, chunk_z_pos];
                     if is_in_render(chunk_pos) {
-                        out_chunk_pos.push(chunk_pos);
+                        out_chunk_pos.push((chunk_pos, lod(chunk_pos)));
                     }
                 }
             }
@@ -222,51 +839,78 @@ impl GameScene {
         out_chunk_pos
     }
 
-    pub fn init_world(&mut self, block_uvs: &RapidHashMap<String, AtlasUV>) {
+    pub fn init_world(&mut self, block_models: &RapidHashMap<String, BakedModel>) {
         let world = self.world.as_mut().unwrap();
 
-        self.build_all_loaded_chunks(block_uvs);
+        self.build_all_loaded_chunks(block_models);
     }
 
-    pub fn build_all_loaded_chunks(&mut self, block_uvs: &RapidHashMap<String, AtlasUV>) {
-        let allocator = self
-            .world
-            .as_ref()
-            .unwrap()
-            .memory_allocator
-            .clone()
-            .unwrap();
-        for (chunk_pos, chunk) in &self.world.as_ref().unwrap().loaded_chunks {
-            let out_chunk_mesh =
-                chunk_mesh::build_chunk_mesh(allocator.clone(), &chunk, &block_uvs);
-            self.chunk_meshes.insert(*chunk_pos, out_chunk_mesh);
+    pub fn build_all_loaded_chunks(&mut self, block_models: &RapidHashMap<String, BakedModel>) {
+        let arena = self.mesh_arena.as_mut().unwrap();
+        let world = self.world.as_ref().unwrap();
+        let mut meshes = Vec::new();
+        for (chunk_pos, chunk) in &world.loaded_chunks {
+            let out_chunk_mesh = chunk_mesh::build_chunk_mesh(
+                arena,
+                chunk,
+                &world.neighbors(*chunk_pos),
+                block_models,
+                self.meshing,
+            );
+            meshes.push((*chunk_pos, out_chunk_mesh));
+        }
+        for (chunk_pos, out_chunk_mesh) in meshes {
+            self.insert_chunk_mesh(chunk_pos, out_chunk_mesh);
         }
     }
 
-    pub fn build_all_unbuild_loaded_chunks(&mut self, block_uvs: &RapidHashMap<String, AtlasUV>) {
-        let allocator = self
-            .world
-            .as_ref()
-            .unwrap()
-            .memory_allocator
-            .clone()
-            .unwrap();
-        for (chunk_pos, chunk) in &self.world.as_ref().unwrap().loaded_chunks {
-            if self.chunk_meshes.contains_key(chunk_pos) {
-                continue;
-            }
+    /// Stores a chunk's mesh, growing the shared quad indices if they don't cover all its quads.
+    /// The mesh it replaces is given back to the mesh arena.
+    fn insert_chunk_mesh(&mut self, chunk_pos: [i32; 3], mesh: Option<ChunkMesh>) {
+        if let (Some(mesh), Some(quad_indices)) = (&mesh, &mut self.quad_indices) {
+            let allocator = self.world.as_ref().unwrap().memory_allocator.clone().unwrap();
+            quad_indices.reserve(allocator, mesh.max_quads());
+        }
+        if let Some(Some(old_mesh)) = self.chunk_meshes.insert(chunk_pos, mesh) {
+            old_mesh.free(self.mesh_arena.as_mut().unwrap());
+        }
+    }
+
+    /// Queues a rebuild of a loaded chunk's mesh against its current neighbors.
+    /// The old mesh stays visible until the new one is uploaded.
+    fn rebuild_chunk_mesh(&mut self, chunk_pos: [i32; 3], urgent: bool) {
+        let world = self.world.as_ref().unwrap();
+        let Some(chunk) = world.loaded_chunks.get(&chunk_pos) else {
+            return;
+        };
 
-            let out_chunk_mesh = chunk_mesh::build_chunk_mesh(allocator.clone(), chunk, block_uvs);
-            self.chunk_meshes.insert(*chunk_pos, out_chunk_mesh);
+        let (neighbors, options) = self.lod_mesh_inputs(chunk_pos);
+        self.workers.mesh(chunk.clone(), neighbors, self.block_models.clone(), options, urgent);
+    }
+
+    /// Neighbors and meshing options for a chunk at its level of detail, along with the level of
+    /// detail of each neighbor. Neighbors without a level of detail yet count as the same level.
+    fn lod_mesh_inputs(&self, chunk_pos: [i32; 3]) -> (ChunkNeighbors, MeshingOptions) {
+        let lod = self.chunk_lods.get(&chunk_pos).copied().unwrap_or(0);
+        let mut neighbors = self.world.as_ref().unwrap().neighbors(chunk_pos);
+        for (slot, offset) in ChunkNeighbors::OFFSETS.iter().enumerate() {
+            let neighbor_pos = [0, 1, 2].map(|axis| chunk_pos[axis] + offset[axis]);
+            neighbors.lods[slot] = self.chunk_lods.get(&neighbor_pos).copied().unwrap_or(lod);
         }
+        (neighbors, MeshingOptions { lod, ..self.meshing })
     }
 
-    fn load_chunk_mesh(&mut self, _res: &SceneResources, chunk: Arc<Chunk>) {
-        let world = self.world.as_mut().unwrap();
-        let allocator = world.memory_allocator.clone().unwrap();
+    /// Uploads meshes the workers finished, up to `MESH_UPLOAD_BUDGET` per frame
+    fn upload_chunk_meshes(&mut self) {
+        for meshed in self.workers.poll_meshed(MESH_UPLOAD_BUDGET) {
+            let arena = self.mesh_arena.as_mut().unwrap();
+            let out_chunk_mesh = chunk_mesh::upload_chunk_vertices(arena, meshed.vertices);
+            self.insert_chunk_mesh(meshed.position, out_chunk_mesh);
+        }
 
-        let out_chunk_mesh = chunk_mesh::build_chunk_mesh(allocator, &chunk, &self.block_uvs);
-        self.chunk_meshes.insert(chunk.position, out_chunk_mesh);
+        let arena = self.mesh_arena.as_mut().unwrap();
+        arena.release_finished();
+        RENDER_STATS.set_arena_stats(arena.stats());
     }
 
     pub fn amount_of_chunk_meshes(&self) -> usize {
@@ -18,7 +18,13 @@ impl Scene for MenuScene
         scene_manager.queue_scene_switch(SceneType::Game);
     }
 
-    fn update(&mut self, _delta_time: f32, _input_state: &mut InputState, _camera: &mut Camera) {
+    fn update(
+        &mut self,
+        _delta_time: f32,
+        _alpha: f32,
+        _input_state: &mut InputState,
+        _camera: &mut Camera,
+    ) {
 
     }
 
@@ -36,6 +42,7 @@ impl Scene for MenuScene
         _viewport: &Viewport,
         _allocator: &StandardMemoryAllocator,
         _resources: &SceneResources,
+        _alpha: f32,
     ) {
         //println!("Drawing Menu Scene");
     }
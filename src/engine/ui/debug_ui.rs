@@ -8,7 +8,13 @@ use std::{collections::VecDeque, sync::Arc, time::Duration};
 use sysinfo::{Process, System};
 use vulkano::device::Queue;
 
-use crate::{engine::core::input, utils::math::Vec3};
+use crate::{
+    engine::{
+        core::input,
+        rendering::{render_stats::RENDER_STATS, vertex::ChunkVertex},
+    },
+    utils::math::Vec3,
+};
 
 type MemoryUsageBytes = u64;
 type CpuUsagePercent = f64;
@@ -281,6 +287,39 @@ impl DebugUi {
 
                     // Game Specifics Header
                     ui.heading("Game Specifics");
+
+                    egui::CollapsingHeader::new("Rendering")
+                        .default_open(true)
+                        .show(ui, |ui| {
+                            let drawn = RENDER_STATS.chunks_drawn();
+                            let culled = RENDER_STATS.chunks_culled();
+                            let draw_calls = RENDER_STATS.chunk_draw_calls();
+                            ui.add_space(5.0);
+                            ui.label(format!("Chunks drawn: {drawn}"));
+                            ui.label(format!("Chunks culled: {culled}"));
+                            ui.label(format!("Chunk draw calls: {draw_calls}"));
+
+                            let arena = RENDER_STATS.arena_stats();
+                            let vertex_size = size_of::<ChunkVertex>() as f64;
+                            let mib = |vertices: u64| vertices as f64 * vertex_size / 1_048_576.0;
+                            ui.label(format!(
+                                "Mesh arena: {:.1} / {:.1} MiB ({:.0}%)",
+                                mib(arena.used),
+                                mib(arena.capacity),
+                                arena.utilization() * 100.0
+                            ));
+                            ui.label(format!(
+                                "Arena fragmentation: {:.0}% ({} free ranges)",
+                                arena.fragmentation() * 100.0,
+                                arena.free_ranges
+                            ));
+
+                            let entities = RENDER_STATS.entities_drawn();
+                            let entity_draw_calls = RENDER_STATS.entity_draw_calls();
+                            ui.label(format!(
+                                "Entities drawn: {entities} ({entity_draw_calls} draw calls)"
+                            ));
+                        });
                 });
             });
     }
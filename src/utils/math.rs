@@ -244,3 +244,134 @@ impl From<glam_mat4> for Mat4 {
         ])
     }
 }
+
+/// Axis aligned bounding box
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: glam_vec3,
+    pub max: glam_vec3,
+}
+
+impl Aabb {
+    pub fn new(min: glam_vec3, max: glam_vec3) -> Self {
+        Self { min, max }
+    }
+
+    /// Whether the boxes overlap, just touching doesn't count
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        self.min.cmplt(other.max).all() && other.min.cmplt(self.max).all()
+    }
+}
+
+/// The six clipping planes of a view-projection, each stored as `(normal, distance)` in a Vec4
+/// with the normal pointing into the frustum.
+/// Planes are extracted from the rows of the matrix (Gribb & Hartmann), using the OpenGL
+/// clip depth range of `-w..w` that `perspective_rh_gl` produces.
+#[derive(Copy, Clone, Debug)]
+pub struct Frustum {
+    pub planes: [glam_vec4; 6],
+}
+
+impl Frustum {
+    pub fn from_view_projection(view_projection: glam_mat4) -> Self {
+        let row = |i| view_projection.row(i);
+
+        let planes = [
+            row(3) + row(0), // left
+            row(3) - row(0), // right
+            row(3) + row(1), // bottom
+            row(3) - row(1), // top
+            row(3) + row(2), // near
+            row(3) - row(2), // far
+        ]
+        .map(|plane| plane / plane.truncate().length());
+
+        Self { planes }
+    }
+
+    /// Checks whether any part of the box might be inside the frustum.
+    /// Tests the corner furthest along each plane's normal, so boxes near the frustum's corners
+    /// can pass without being visible, but a visible box is never rejected.
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        self.planes.iter().all(|plane| {
+            let normal = plane.truncate();
+            let furthest = glam_vec3::select(normal.cmpge(glam_vec3::ZERO), aabb.max, aabb.min);
+            normal.dot(furthest) + plane.w >= 0.0
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Camera at the origin looking down -z with a 90 degree field of view, so the frustum's
+    /// sides are at `|x| = -z` and `|y| = -z`
+    fn frustum() -> Frustum {
+        let projection = glam_mat4::perspective_rh_gl(90f32.to_radians(), 1.0, 0.1, 100.0);
+        let view = glam_mat4::look_at_rh(glam_vec3::ZERO, glam_vec3::NEG_Z, glam_vec3::Y);
+        Frustum::from_view_projection(projection * view)
+    }
+
+    fn aabb(min: [f32; 3], max: [f32; 3]) -> Aabb {
+        Aabb::new(glam_vec3::from(min), glam_vec3::from(max))
+    }
+
+    #[test]
+    fn box_inside_is_visible() {
+        assert!(frustum().intersects_aabb(&aabb([-1.0, -1.0, -11.0], [1.0, 1.0, -9.0])));
+    }
+
+    #[test]
+    fn boxes_outside_are_culled() {
+        let frustum = frustum();
+        // off to each side, below and above
+        assert!(!frustum.intersects_aabb(&aabb([12.0, -1.0, -11.0], [14.0, 1.0, -9.0])));
+        assert!(!frustum.intersects_aabb(&aabb([-14.0, -1.0, -11.0], [-12.0, 1.0, -9.0])));
+        assert!(!frustum.intersects_aabb(&aabb([-1.0, -14.0, -11.0], [1.0, -12.0, -9.0])));
+        assert!(!frustum.intersects_aabb(&aabb([-1.0, 12.0, -11.0], [1.0, 14.0, -9.0])));
+        // past the far plane
+        assert!(!frustum.intersects_aabb(&aabb([-1.0, -1.0, -150.0], [1.0, 1.0, -120.0])));
+    }
+
+    #[test]
+    fn box_behind_the_camera_is_culled() {
+        let frustum = frustum();
+        assert!(!frustum.intersects_aabb(&aabb([-1.0, -1.0, 5.0], [1.0, 1.0, 7.0])));
+        // large enough to cover the frustum's sides if they went on behind the camera
+        assert!(!frustum.intersects_aabb(&aabb([-50.0, -50.0, 1.0], [50.0, 50.0, 3.0])));
+    }
+
+    #[test]
+    fn boxes_straddling_a_plane_are_visible() {
+        let frustum = frustum();
+        // across the left side, the far plane and the near plane
+        assert!(frustum.intersects_aabb(&aabb([-12.0, -1.0, -11.0], [-8.0, 1.0, -9.0])));
+        assert!(frustum.intersects_aabb(&aabb([-1.0, -1.0, -110.0], [1.0, 1.0, -90.0])));
+        assert!(frustum.intersects_aabb(&aabb([-1.0, -1.0, -1.0], [1.0, 1.0, 1.0])));
+    }
+
+    #[test]
+    fn point_boxes_are_tested_against_every_plane() {
+        let frustum = frustum();
+        let point = |p: [f32; 3]| frustum.intersects_aabb(&aabb(p, p));
+        assert!(point([0.0, 0.0, -10.0]));
+        assert!(point([9.0, -9.0, -10.0]));
+        assert!(!point([11.0, 0.0, -10.0]));
+        assert!(!point([0.0, 0.0, 10.0]));
+        assert!(!point([0.0, 0.0, -0.05]));
+        assert!(!point([0.0, 0.0, -101.0]));
+
+        // flat on one axis, like a box around a single quad
+        assert!(frustum.intersects_aabb(&aabb([-1.0, 0.0, -11.0], [1.0, 0.0, -9.0])));
+        assert!(!frustum.intersects_aabb(&aabb([12.0, 0.0, -11.0], [13.0, 0.0, -9.0])));
+    }
+
+    #[test]
+    fn touching_boxes_do_not_intersect() {
+        let a = aabb([0.0, 0.0, 0.0], [1.0, 1.0, 1.0]);
+        assert!(a.intersects(&aabb([0.5, 0.5, 0.5], [2.0, 2.0, 2.0])));
+        assert!(!a.intersects(&aabb([1.0, 0.0, 0.0], [2.0, 1.0, 1.0])));
+        assert!(!a.intersects(&aabb([3.0, 3.0, 3.0], [4.0, 4.0, 4.0])));
+    }
+}